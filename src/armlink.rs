@@ -0,0 +1,271 @@
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::lines::MapLines;
+use crate::model::{Fill, MapFile, MemoryConfig, MemoryMap, ObjectSize, SubSection, Symbol};
+use crate::util::{parse_hex, split_archive_member, split_token, thumb_address};
+
+#[derive(Debug)]
+enum ParserState {
+    None,
+    Skipped,
+    SymbolTable,
+    MemoryMap,
+    ComponentSizes,
+}
+
+/// Parse an Arm Compiler armlink map file (`--map --list`). Execution regions become
+/// output sections and memory regions, the sections placed in them input sections.
+pub fn parse<R: BufRead>(lines: &mut MapLines<R>) -> Result<MapFile, ParseError> {
+    let mut memory_configuration: Vec<MemoryConfig> = Vec::new();
+    let mut memory_map: Vec<MemoryMap> = Vec::new();
+    let mut symbols: Vec<(String, Symbol)> = Vec::new();
+    let mut object_sizes: Vec<ObjectSize> = Vec::new();
+
+    let mut parser_state = ParserState::None;
+    // Set by the component size headings, rows of the "Library Name" table are totals
+    let mut object_rows = false;
+    // armlink before Arm Compiler 5 prints `Base Addr` without the load address column
+    let mut load_address_column = true;
+
+    while let Some(line) = lines.next_line()? {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Image Symbol Table") {
+            parser_state = ParserState::SymbolTable;
+            continue;
+        } else if trimmed.starts_with("Memory Map of the image") {
+            parser_state = ParserState::MemoryMap;
+            continue;
+        } else if trimmed.starts_with("Image component sizes") {
+            parser_state = ParserState::ComponentSizes;
+            continue;
+        } else if ["Section Cross References", "Removing Unused input sections", "Adding Veneers"]
+            .iter()
+            .any(|heading| trimmed.starts_with(heading))
+        {
+            parser_state = ParserState::Skipped;
+            continue;
+        }
+
+        if trimmed.is_empty() || trimmed.starts_with("====") || trimmed.starts_with("----") {
+            continue;
+        }
+
+        match parser_state {
+            ParserState::SymbolTable => {
+                if trimmed.ends_with(" Symbols") || trimmed.starts_with("Symbol Name") || trimmed.starts_with("Sym ") {
+                    // `Local Symbols`, `Global Symbols` and `Mapping Symbols` headings and their column headers
+                } else if trimmed.contains(" 0x") {
+                    if let Some(symbol) = parse_symbol_line(trimmed)? {
+                        symbols.push(symbol);
+                    }
+                } else {
+                    lines.unrecognized()?;
+                }
+            }
+            ParserState::MemoryMap => {
+                if let Some(region) = trimmed.strip_prefix("Execution Region ") {
+                    let (name, attributes) = split_token(region);
+                    let address = region_field(attributes, "Exec base").or_else(|| region_field(attributes, "Base"));
+                    let load_address = region_field(attributes, "Load base");
+                    let length = region_field(attributes, "Size");
+                    memory_configuration.push(MemoryConfig {
+                        name: name.to_string(),
+                        origin: address.unwrap_or(0),
+                        length: region_field(attributes, "Max").unwrap_or(0),
+                        attribute: attributes
+                            .trim_matches(|c| c == '(' || c == ')')
+                            .rsplit(", ")
+                            .next()
+                            .unwrap_or("")
+                            .to_string(),
+                        ..Default::default()
+                    });
+                    memory_map.push(MemoryMap {
+                        section: name.to_string(),
+                        section_address: address,
+                        section_length: length,
+                        load_address: load_address.filter(|&load_address| Some(load_address) != address),
+                        ..Default::default()
+                    });
+                } else if trimmed.starts_with("Exec Addr") || trimmed.starts_with("Base Addr") {
+                    load_address_column = trimmed.contains("Load Addr");
+                } else if trimmed.starts_with("0x") {
+                    parse_memory_map_line(trimmed, load_address_column, &mut memory_map)?;
+                } else if !(trimmed.starts_with("Load Region ") || trimmed.starts_with("Image Entry point")) {
+                    lines.unrecognized()?;
+                }
+            }
+            ParserState::ComponentSizes => {
+                if trimmed.starts_with("Code (inc. data)") {
+                    object_rows = trimmed.ends_with("Object Name") || trimmed.ends_with("Library Member Name");
+                } else if !trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+                    // `Total RO  Size (Code + RO Data)  568 (  0.55kB)` closes the sizes
+                    if !trimmed.starts_with("Total ") {
+                        lines.unrecognized()?;
+                    }
+                } else if object_rows {
+                    parse_object_size_line(trimmed, &mut object_sizes)?;
+                } else {
+                    // Rows of the library and grand totals tables are not read
+                }
+            }
+            ParserState::None => {
+                // The `Component: ARM Compiler 6.16 Tool: armlink` banner
+                if !trimmed.starts_with("Component:") {
+                    lines.unrecognized()?;
+                }
+            }
+            ParserState::Skipped => {
+                // Tables which are not read, such as the section cross references
+            }
+        }
+    }
+
+    attach_symbols(&mut memory_map, symbols);
+
+    Ok(MapFile {
+        memory_configuration,
+        memory_map,
+        object_sizes,
+        ..Default::default()
+    })
+}
+
+/// Read `Key: 0x...` from the attributes of a region heading.
+fn region_field(attributes: &str, key: &str) -> Option<u64> {
+    attributes
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(", ")
+        .find_map(|field| field.strip_prefix(key)?.strip_prefix(": "))
+        .and_then(parse_hex)
+}
+
+/// `name  0x08000189  Thumb Code  8  startup.o(.text)`, returned with its object and section
+fn parse_symbol_line(line: &str) -> Result<Option<(String, Symbol)>, ParseError> {
+    // The name may be longer than its column, so look for the value instead
+    let Some(value_start) = line.find(" 0x") else {
+        return Ok(None);
+    };
+    let name = line[..value_start].trim();
+    let (value, rest) = split_token(&line[value_start..]);
+    let value = parse_hex(value).ok_or_else(|| ParseError::invalid_number(value))?;
+
+    // The type is one or two words followed by the size in decimal
+    let mut kind = String::new();
+    let mut rest = rest;
+    let size = loop {
+        let (token, remainder) = split_token(rest);
+        if token.is_empty() {
+            return Ok(None);
+        }
+        rest = remainder;
+        if let Ok(size) = token.parse::<u64>() {
+            break size;
+        }
+        kind.push_str(token);
+    };
+    if !(kind.ends_with("Code") || kind == "Data") {
+        // Section, Number (absolute) and file name entries are not symbols of a section
+        return Ok(None);
+    }
+
+    // Thumb function addresses have bit 0 set
+    let address = if kind == "ThumbCode" { thumb_address(value) } else { value };
+    Ok(Some((
+        rest.trim().to_string(),
+        Symbol {
+            address,
+            name: name.to_string(),
+            size,
+        },
+    )))
+}
+
+/// `Exec Addr  Load Addr  Size  Type  Attr  Idx  E  Section Name  Object`
+fn parse_memory_map_line(line: &str, load_address_column: bool, memory_map: &mut [MemoryMap]) -> Result<(), ParseError> {
+    let Some(last_map) = memory_map.last_mut() else {
+        return Ok(());
+    };
+    let (address, rest) = split_token(line);
+    let address = parse_hex(address).ok_or_else(|| ParseError::invalid_number(address))?;
+    // The load address is `-` for zero initialized data
+    let (load_address, rest) = if load_address_column { split_token(rest) } else { ("", rest) };
+    let (size, rest) = split_token(rest);
+    let size = parse_hex(size).ok_or_else(|| ParseError::invalid_number(size))?;
+
+    // Zero initialized data takes no space in the load region
+    if let (Some(region_load_address), Some(load_address)) = (last_map.load_address, parse_hex(load_address)) {
+        let load_length = (load_address + size).saturating_sub(region_load_address);
+        last_map.load_length = Some(last_map.load_length.unwrap_or(0).max(load_length));
+    }
+
+    let (kind, rest) = split_token(rest);
+    if kind == "PAD" {
+        last_map.fills.push(Fill {
+            address,
+            size,
+            pattern: None,
+        });
+        return Ok(());
+    }
+
+    let (_attr, rest) = split_token(rest);
+    let (_idx, rest) = split_token(rest);
+    // Sections containing the entry point are marked with `*`
+    let rest = rest.strip_prefix('*').unwrap_or(rest);
+    let (section, object) = split_token(rest);
+    last_map.sub_section.push(SubSection {
+        name: vec![section.to_string()],
+        address: Some(address),
+        length: Some(size),
+        object: object.trim_end().to_string(),
+        ..Default::default()
+    });
+    Ok(())
+}
+
+/// `Code  (inc. data)  RO Data  RW Data  ZI Data  Debug  Object Name`
+fn parse_object_size_line(line: &str, object_sizes: &mut Vec<ObjectSize>) -> Result<(), ParseError> {
+    let mut numbers = [0u64; 6];
+    let mut rest = line;
+    for number in &mut numbers {
+        let (token, remainder) = split_token(rest);
+        *number = token.parse().map_err(|_| ParseError::invalid_number(token))?;
+        rest = remainder;
+    }
+    let object = rest.trim();
+    if object.ends_with("Totals") || object.starts_with("(incl.") {
+        return Ok(());
+    }
+    let [code, inc_data, ro_data, rw_data, zi_data, debug] = numbers;
+    object_sizes.push(ObjectSize {
+        object: object.to_string(),
+        code,
+        inc_data,
+        ro_data,
+        rw_data,
+        zi_data,
+        debug,
+    });
+    Ok(())
+}
+
+/// The symbol table comes before the memory map, so symbols are attached to their
+/// input section once everything is read. Library members are listed by member name
+/// in the symbol table and as `library(member)` in the memory map.
+fn attach_symbols(memory_map: &mut [MemoryMap], symbols: Vec<(String, Symbol)>) {
+    for (object_section, symbol) in symbols {
+        let (object, section) = split_archive_member(&object_section);
+        let sub_section = memory_map
+            .iter_mut()
+            .flat_map(|m| m.sub_section.iter_mut())
+            .find(|sub_section| {
+                (sub_section.object == object || split_archive_member(&sub_section.object).1 == object)
+                    && sub_section.name.iter().any(|name| name == section)
+            });
+        if let Some(sub_section) = sub_section {
+            sub_section.symbols.push(symbol);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+use std::io;
+
+/// What went wrong while parsing a map file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The underlying reader failed.
+    Io,
+    /// An address or length could not be read as a number.
+    InvalidNumber(String),
+    /// A line was not recognized, in strict mode.
+    UnrecognizedLine,
+    /// The format of the map file could not be detected from its first lines.
+    UnknownFormat,
+}
+
+/// Error returned when a map file cannot be parsed, with the line it was found on.
+#[derive(Debug)]
+pub struct ParseError {
+    kind: ErrorKind,
+    line: Option<usize>,
+    text: String,
+    source: Option<io::Error>,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ErrorKind) -> Self {
+        ParseError {
+            kind,
+            line: None,
+            text: String::new(),
+            source: None,
+        }
+    }
+
+    pub(crate) fn invalid_number(text: &str) -> Self {
+        ParseError::new(ErrorKind::InvalidNumber(text.to_string()))
+    }
+
+    /// Set the line the error was found on, unless it is already known.
+    pub(crate) fn at_line(mut self, line: usize, text: &str) -> Self {
+        if self.line.is_none() {
+            self.line = Some(line);
+            self.text = text.to_string();
+        }
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Line number, starting at 1, of the offending line.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Text of the offending line.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.kind, &self.source) {
+            (ErrorKind::Io, Some(e)) => write!(f, "Error reading map file: {}", e)?,
+            (ErrorKind::Io, None) => write!(f, "Error reading map file")?,
+            (ErrorKind::InvalidNumber(number), _) => write!(f, "Invalid number '{}'", number)?,
+            (ErrorKind::UnrecognizedLine, _) => write!(f, "Unrecognized line")?,
+            (ErrorKind::UnknownFormat, _) => write!(f, "Unrecognized map file format")?,
+        }
+        if let Some(line) = self.line {
+            write!(f, " at line {}", line)?;
+        }
+        if !self.text.is_empty() {
+            write!(f, ": {}", self.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError {
+            source: Some(e),
+            ..ParseError::new(ErrorKind::Io)
+        }
+    }
+}
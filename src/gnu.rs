@@ -0,0 +1,532 @@
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::lines::MapLines;
+use crate::model::{
+    ArchiveMember, CommonSymbol, CrossReference, DiscardedSection, Fill, InputFile, MapFile, MemoryConfig, MemoryMap, OutputFile, SubSection, Symbol,
+    SymbolAssignment,
+};
+use crate::util::{infer_symbol_sizes, parse_assignment_statement, parse_hex, split_archive_member, split_token};
+
+// Define an enum for parser states
+#[derive(Debug)]
+enum ParserState {
+    None,
+    Skipped,
+    ArchiveMembers,
+    CommonSymbols,
+    DiscardedSections,
+    MemoryConfiguration,
+    LinkerScriptMemoryMap,
+    CrossReferenceTable,
+}
+
+/// Section names shorter than this are printed on the same line as their address.
+const SECTION_NAME_COLUMN_WIDTH: usize = 16;
+
+/// Width of the size column of the memory map, which ld and gold print with `%W` on
+/// 32-bit and 64-bit targets alike.
+const SIZE_COLUMN_WIDTH: usize = 10;
+
+/// Column of the referencing file in the archive member table.
+const ARCHIVE_REFERENCE_COLUMN: usize = 30;
+
+/// Width of the symbol column in the cross reference table.
+const CREF_SYMBOL_COLUMN: usize = 50;
+
+pub fn parse<R: BufRead>(lines: &mut MapLines<R>) -> Result<MapFile, ParseError> {
+    let mut archive_members: Vec<ArchiveMember> = Vec::new();
+    let mut common_symbols: Vec<CommonSymbol> = Vec::new();
+    let mut discarded_sections: Vec<DiscardedSection> = Vec::new();
+    let mut memory_configuration: Vec<MemoryConfig> = Vec::new();
+    let mut linker_script_memory_map: Vec<MemoryMap> = Vec::new();
+    let mut cross_references: Vec<CrossReference> = Vec::new();
+    let mut input_files: Vec<InputFile> = Vec::new();
+    let mut assignments: Vec<SymbolAssignment> = Vec::new();
+    let mut output = None;
+
+    let mut parser_state = ParserState::None;
+    let mut memory_map_parser_state = 0;
+    let mut group = None;
+    let mut in_output_section = false;
+    let mut group_count = 0;
+
+    while let Some(line) = lines.next_line()? {
+
+        // gold uses its own headings for the archive member table and the memory map
+        if line.starts_with("Archive member included to satisfy reference by file")
+            || line.starts_with("Archive member included because of file") {
+            parser_state = ParserState::ArchiveMembers;
+            continue;
+        } else if line.starts_with("As-needed library included to satisfy reference by file")
+            || line.starts_with("Merging program properties") {
+            parser_state = ParserState::Skipped;
+            continue;
+        } else if line.starts_with("Allocating common symbols") {
+            parser_state = ParserState::CommonSymbols;
+            continue;
+        } else if line.starts_with("Discarded input sections") {
+            parser_state = ParserState::DiscardedSections;
+            continue;
+        } else if line.starts_with("Memory Configuration") {
+            parser_state = ParserState::MemoryConfiguration;
+            continue;
+        } else if line.starts_with("Linker script and memory map") || line.trim_end() == "Memory map" {
+            parser_state = ParserState::LinkerScriptMemoryMap;
+            continue;
+        } else if line.starts_with("Cross Reference Table") {
+            parser_state = ParserState::CrossReferenceTable;
+            continue;
+        }
+
+        match parser_state {
+            ParserState::ArchiveMembers => {
+                if !line.trim().is_empty() && !parse_archive_member_line(&line, &mut archive_members) {
+                    lines.unrecognized()?;
+                }
+            }
+            ParserState::CommonSymbols => {
+                if !(line.trim().is_empty() || line.starts_with("Common symbol")) {
+                    parse_common_symbol_line(&line, &mut common_symbols)?;
+                }
+            }
+            ParserState::DiscardedSections => {
+                if !line.trim().is_empty() {
+                    parse_discarded_section_line(&line, &mut discarded_sections)?;
+                }
+            }
+            ParserState::MemoryConfiguration => {
+                if !(line.trim().is_empty()
+                    || line.starts_with("*default*")
+                    || line.starts_with("Name             Origin             Length             Attributes")
+                ) {
+                    let mut split_line = line.split_whitespace();
+                    let name = split_line.next().unwrap_or("").to_string();
+                    let origin = split_line.next().unwrap_or("");
+                    let length = split_line.next().unwrap_or("");
+                    let mem_config = MemoryConfig {
+                        name,
+                        origin: parse_hex(origin).ok_or_else(|| ParseError::invalid_number(origin))?,
+                        length: parse_hex(length).ok_or_else(|| ParseError::invalid_number(length))?,
+                        attribute: split_line.collect::<Vec<_>>().join(" "),
+                        ..Default::default()
+                    };
+                    memory_configuration.push(mem_config);
+                }
+            }
+            ParserState::LinkerScriptMemoryMap => {
+                if let Some(path) = line.strip_prefix("LOAD ") {
+                    // `LOAD linker stubs` is the file ld creates for its own stubs
+                    if path.trim() == "linker stubs" {
+                        continue;
+                    }
+                    input_files.push(InputFile {
+                        path: path.trim().to_string(),
+                        group,
+                    });
+                } else if line.starts_with("START GROUP") {
+                    group = Some(group_count);
+                    group_count += 1;
+                } else if line.starts_with("END GROUP") {
+                    group = None;
+                } else if let Some(output_line) = line.strip_prefix("OUTPUT(").and_then(|l| l.strip_suffix(')')) {
+                    // The path may contain spaces, the BFD target never does
+                    let (file, target) = output_line.rsplit_once(' ').unwrap_or((output_line, ""));
+                    output = Some(OutputFile {
+                        file: file.to_string(),
+                        target: target.to_string(),
+                    });
+                } else if line.trim().is_empty() {
+                    // Output sections are followed by an empty line
+                    in_output_section = false;
+                } else if let Some(fill) = line.trim_start().strip_prefix("*fill*") {
+                    let mut split_line = fill.split_whitespace();
+                    let address = split_line.next().unwrap_or("");
+                    let size = split_line.next().unwrap_or("");
+                    let pattern = split_line.collect::<Vec<_>>().join("");
+                    if let Some(last_map) = linker_script_memory_map.last_mut() {
+                        last_map.fills.push(Fill {
+                            address: parse_hex(address).ok_or_else(|| ParseError::invalid_number(address))?,
+                            size: parse_hex(size).ok_or_else(|| ParseError::invalid_number(size))?,
+                            pattern: (!pattern.is_empty()).then_some(pattern),
+                        });
+                    }
+                } else if let Some((address, size, value)) = split_data_statement(&line) {
+                    if let Some(last_map) = linker_script_memory_map.last_mut() {
+                        last_map.fills.push(Fill {
+                            address: parse_hex(address).ok_or_else(|| ParseError::invalid_number(address))?,
+                            size: parse_hex(size).ok_or_else(|| ParseError::invalid_number(size))?,
+                            pattern: Some(value.trim_start_matches("0x").to_string()),
+                        });
+                    }
+                } else if let Some(assignment) = parse_assignment(&line) {
+                    match linker_script_memory_map.last_mut() {
+                        Some(last_map) if in_output_section => last_map.assignments.push(assignment),
+                        _ => assignments.push(assignment),
+                    }
+                } else if is_skipped_line(&line) {
+                    // Input section descriptions, fill statements and sizes before relaxing are not read
+                } else {
+                    if !line.starts_with(' ') {
+                        in_output_section = true;
+                    }
+                    if !parse_memory_map_line(&line, &mut linker_script_memory_map, &mut memory_map_parser_state) {
+                        lines.unrecognized()?;
+                    }
+                }
+            }
+            ParserState::CrossReferenceTable => {
+                if !(line.trim().is_empty() || (line.starts_with("Symbol") && line.trim_end().ends_with("File"))) {
+                    parse_cross_reference_line(&line, &mut cross_references);
+                }
+            }
+            ParserState::Skipped => {
+                // Shared libraries linked as needed and removed program properties are not read
+            }
+            ParserState::None => {
+                if !line.trim().is_empty() {
+                    lines.unrecognized()?;
+                }
+            }
+        }
+    }
+
+    for sub_section in linker_script_memory_map.iter_mut().flat_map(|m| m.sub_section.iter_mut()) {
+        infer_symbol_sizes(sub_section);
+    }
+
+    Ok(MapFile {
+        archive_members,
+        common_symbols,
+        discarded_sections,
+        memory_configuration,
+        memory_map: linker_script_memory_map,
+        cross_references,
+        input_files,
+        output,
+        assignments,
+        ..Default::default()
+    })
+}
+
+/// Returns false when the line is neither an `archive(member)` nor the reference of one.
+fn parse_archive_member_line(line: &str, archive_members: &mut Vec<ArchiveMember>) -> bool {
+    let reference = if line.starts_with(' ') {
+        // Reference of a member name too long for its column
+        if !archive_members.last().is_some_and(|archive_member| archive_member.referenced_by.is_empty()) {
+            return false;
+        }
+        line.trim()
+    } else {
+        // A member path longer than the column may have a space where the column ends,
+        // but the member column always ends with the `)` of `archive(member)`
+        let (member, reference) = match line.get(ARCHIVE_REFERENCE_COLUMN..) {
+            Some(reference)
+                if line[..ARCHIVE_REFERENCE_COLUMN].ends_with(' ')
+                    && line[..ARCHIVE_REFERENCE_COLUMN].trim_end().ends_with(')') =>
+            {
+                (line[..ARCHIVE_REFERENCE_COLUMN].trim(), reference.trim())
+            }
+            _ => (line.trim(), ""),
+        };
+        let (archive, member) = split_archive_member(member);
+        if member.is_empty() {
+            return false;
+        }
+        archive_members.push(ArchiveMember {
+            archive: archive.to_string(),
+            member: member.to_string(),
+            ..Default::default()
+        });
+        reference
+    };
+
+    if reference.is_empty() {
+        return true;
+    }
+    // `file (symbol)`, where the file may itself be an archive member
+    let (referenced_by, symbol) = match reference.rsplit_once(" (") {
+        Some((file, symbol)) => (file, symbol.strip_suffix(')').unwrap_or(symbol)),
+        None => (reference, ""),
+    };
+    if let Some(archive_member) = archive_members.last_mut() {
+        archive_member.referenced_by = referenced_by.trim().to_string();
+        archive_member.symbol = symbol.to_string();
+    }
+    true
+}
+
+fn parse_common_symbol_line(line: &str, common_symbols: &mut Vec<CommonSymbol>) -> Result<(), ParseError> {
+    let rest = if line.starts_with(' ') {
+        // Size and file of a symbol too long for its column
+        line.trim_start()
+    } else {
+        let (symbol, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        common_symbols.push(CommonSymbol {
+            symbol: symbol.to_string(),
+            ..Default::default()
+        });
+        rest.trim_start()
+    };
+
+    if rest.is_empty() {
+        return Ok(());
+    }
+    let (size, file) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if let Some(common_symbol) = common_symbols.last_mut() {
+        common_symbol.size = parse_hex(size).ok_or_else(|| ParseError::invalid_number(size))?;
+        common_symbol.file = file.trim().to_string();
+    }
+    Ok(())
+}
+
+fn parse_discarded_section_line(line: &str, discarded_sections: &mut Vec<DiscardedSection>) -> Result<(), ParseError> {
+    let rest = if line.starts_with("                ") {
+        // Address, size and object of a section name too long for its column
+        line.trim_start()
+    } else {
+        let (section, rest) = split_token(line);
+        discarded_sections.push(DiscardedSection {
+            section: section.to_string(),
+            ..Default::default()
+        });
+        rest
+    };
+
+    if rest.is_empty() {
+        return Ok(());
+    }
+    let (address, rest) = split_token(rest);
+    let (size, object) = split_token(rest);
+    if let Some(discarded_section) = discarded_sections.last_mut() {
+        discarded_section.address = parse_hex(address).ok_or_else(|| ParseError::invalid_number(address))?;
+        discarded_section.size = parse_hex(size).ok_or_else(|| ParseError::invalid_number(size))?;
+        discarded_section.object = object.trim_end().to_string();
+    }
+    Ok(())
+}
+
+fn parse_cross_reference_line(line: &str, cross_references: &mut Vec<CrossReference>) {
+    if line.starts_with(' ') {
+        // Further file of the current symbol
+        let file = line.trim();
+        if let Some(cross_reference) = cross_references.last_mut() {
+            if cross_reference.defined_in.is_empty() {
+                cross_reference.defined_in = file.to_string();
+            } else {
+                cross_reference.referenced_by.push(file.to_string());
+            }
+        }
+        return;
+    }
+
+    // A symbol longer than the column is printed alone, its files follow on the next lines
+    let (symbol, file) = match line.get(CREF_SYMBOL_COLUMN..) {
+        Some(file) if line[..CREF_SYMBOL_COLUMN].ends_with(' ') => (line[..CREF_SYMBOL_COLUMN].trim(), file.trim()),
+        _ => (line.trim(), ""),
+    };
+    cross_references.push(CrossReference {
+        symbol: symbol.to_string(),
+        defined_in: file.to_string(),
+        referenced_by: Vec::new(),
+    });
+}
+
+/// Returns false when the line is neither a section, an input section nor a symbol.
+fn parse_memory_map_line(line: &str, linker_script_memory_map: &mut Vec<MemoryMap>, memory_map_parser_state: &mut i32) -> bool {
+    let mut section = String::new();
+    let mut sub_section = String::new();
+    let mut address = String::new();
+    let mut length = String::new();
+    let mut object = String::new();
+
+    let mut name = line;
+    let columns = line.get(SECTION_NAME_COLUMN_WIDTH..).unwrap_or("");
+
+    if columns.starts_with("0x") {
+        name = &line[..SECTION_NAME_COLUMN_WIDTH];
+        // Address exists. The address column is 10 characters on 32-bit targets and
+        // 18 characters on 64-bit targets.
+        let address_width = columns.find(' ').unwrap_or(columns.len());
+        address = columns[..address_width].to_string();
+
+        let size_end = address_width + 1 + SIZE_COLUMN_WIDTH;
+        let size_column = columns.get(address_width + 1..size_end).unwrap_or("");
+        let size = size_column.trim();
+        if parse_hex(size).is_some() && size_column.ends_with(size) {
+            // Lenth exist
+            length = size.to_string();
+            // object follows the size column
+            object = columns.get(size_end..).unwrap_or("").trim().to_string();
+        } else {
+            let (size, rest) = split_token(&columns[address_width..]);
+            if parse_hex(size).is_some() {
+                // A size wider than its column, such as `0x100000000`, shifts the object
+                length = size.to_string();
+                object = rest.trim_end().to_string();
+            } else {
+                // symbol name follows the address column
+                object = columns[address_width..].trim().to_string();
+            }
+        }
+    }
+
+    if !name.starts_with("  ") {
+        if !name.starts_with(' ') {
+            // section name
+            section = name.split_whitespace().next().unwrap_or("").to_string();
+        } else {
+            // subsection name
+            sub_section = name.split_whitespace().next().unwrap_or("").to_string();
+        }
+    }
+
+    if !section.is_empty() {
+        let memory_map = if !address.is_empty() && !length.is_empty() {
+            *memory_map_parser_state = 2;
+            MemoryMap {
+                section: section.clone(),
+                section_address: parse_hex(&address),
+                section_length: parse_hex(&length),
+                load_address: parse_load_address(&object),
+                ..Default::default()
+            }
+        } else {
+            *memory_map_parser_state = 1;
+            MemoryMap {
+                section: section.clone(),
+                ..Default::default()
+            }
+        };
+        linker_script_memory_map.push(memory_map);
+    } else if !sub_section.is_empty() {
+        if *memory_map_parser_state < 2 {
+            return false;
+        }
+        if *memory_map_parser_state == 2 {
+            if let Some(last_map) = linker_script_memory_map.last_mut() {
+                // An input section of the same name at the same address comes from another
+                // object and is kept apart
+                let subsection_overlap = last_map.sub_section.last().is_some_and(|last_sub_section| {
+                    last_sub_section.name.contains(&sub_section)
+                        && (!address.is_empty() && last_sub_section.address == parse_hex(&address))
+                });
+                match last_map.sub_section.last_mut() {
+                    Some(last_sub_section) if !subsection_overlap => last_sub_section.name.push(sub_section.clone()),
+                    _ => last_map.sub_section.push(SubSection {
+                        name: vec![sub_section.clone()],
+                        ..Default::default()
+                    }),
+                }
+            }
+
+            if !address.is_empty() && !length.is_empty() && !object.is_empty() {
+                if let Some(last_sub_section) = linker_script_memory_map.last_mut().and_then(|m| m.sub_section.last_mut()) {
+                    last_sub_section.address = parse_hex(&address);
+                    last_sub_section.length = parse_hex(&length);
+                    last_sub_section.object = object.clone();
+                }
+                *memory_map_parser_state = 3;
+            }
+        } else if *memory_map_parser_state == 3
+            && let Some(last_map) = linker_script_memory_map.last_mut() {
+            // Each input section is kept apart, also when it overlaps the previous one
+            let mut new_sub_section = SubSection {
+                name: vec![sub_section.clone()],
+                ..Default::default()
+            };
+
+            if address.is_empty() && length.is_empty() {
+                *memory_map_parser_state = 2
+            } else if !address.is_empty() && !length.is_empty() {
+                new_sub_section.address = parse_hex(&address);
+                new_sub_section.length = parse_hex(&length);
+                if !object.is_empty() {
+                    new_sub_section.object = object.clone();
+                }
+                *memory_map_parser_state = 3
+            }
+
+            last_map.sub_section.push(new_sub_section);
+        }
+    } else if *memory_map_parser_state == 1
+        && !address.is_empty() && !length.is_empty()
+        && let Some(last_map) = linker_script_memory_map.last_mut() {
+        last_map.section_address = parse_hex(&address);
+        last_map.section_length = parse_hex(&length);
+        last_map.load_address = parse_load_address(&object);
+        *memory_map_parser_state = 2;
+    } else if *memory_map_parser_state == 2
+        && !address.is_empty() && !length.is_empty() && !object.is_empty()
+        && let Some(last_sub_section) = linker_script_memory_map.last_mut().and_then(|m| m.sub_section.last_mut()) {
+        last_sub_section.address = parse_hex(&address);
+        last_sub_section.length = parse_hex(&length);
+        last_sub_section.object = object.clone();
+
+        *memory_map_parser_state = 3;
+    } else if *memory_map_parser_state == 3
+        && !address.is_empty() && length.is_empty() && !object.is_empty()
+        && let Some(last_sub_section) = linker_script_memory_map.last_mut().and_then(|m| m.sub_section.last_mut())
+        && let Some(address) = parse_hex(&address) {
+        last_sub_section.symbols.push(Symbol {
+            address,
+            name: object.clone(),
+            size: 0,
+        });
+    } else {
+        return false;
+    }
+    true
+}
+
+/// Output sections copied at startup are followed by `load address 0x...`
+fn parse_load_address(text: &str) -> Option<u64> {
+    parse_hex(text.strip_prefix("load address")?.trim())
+}
+
+/// Input section descriptions echoed from the linker script, such as ` *(.text*)`,
+/// ` KEEP (*(.isr_vector))` or ` *crtbegin.o(.ctors)`, and the `0x4a (size before relaxing)`
+/// line of an input section which relaxation made smaller. The ` FILL mask 0xff` statement
+/// only sets the pattern of the `*fill*` lines which follow.
+fn is_skipped_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    if (trimmed.starts_with("0x") && trimmed.ends_with("(size before relaxing)")) || trimmed.starts_with("FILL mask ") {
+        return true;
+    }
+    line.starts_with(' ')
+        && trimmed.ends_with(')')
+        && (trimmed.starts_with('*')
+            || ["KEEP", "SORT", "EXCLUDE_FILE", "INPUT_SECTION_FLAGS"]
+                .iter()
+                .any(|keyword| trimmed.starts_with(keyword)))
+}
+
+/// `0x0000013c  0x4 LONG 0x12345678` as the address, size and value of the data written by
+/// a `BYTE`, `SHORT`, `LONG`, `QUAD` or `SQUAD` statement of the linker script.
+fn split_data_statement(line: &str) -> Option<(&str, &str, &str)> {
+    if !line.starts_with(' ') {
+        return None;
+    }
+    let (address, rest) = split_token(line);
+    let (size, rest) = split_token(rest);
+    let (keyword, rest) = split_token(rest);
+    let (value, _) = split_token(rest);
+    (address.starts_with("0x") && ["BYTE", "SHORT", "LONG", "QUAD", "SQUAD"].contains(&keyword))
+        .then_some((address, size, value))
+}
+
+/// Linker script assignments are printed in the symbol column, e.g. `_etext = .`
+fn parse_assignment(line: &str) -> Option<SymbolAssignment> {
+    if !line.starts_with(' ') {
+        return None;
+    }
+    let (value, statement) = line.trim().split_once(char::is_whitespace)?;
+    let address = if value == "[!provide]" {
+        // PROVIDE of a symbol which is never referenced
+        None
+    } else {
+        Some(parse_hex(value)?)
+    };
+
+    parse_assignment_statement(address, statement)
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::lines::MapLines;
+use crate::model::{MapFile, MemoryConfig, MemoryMap, ObjectSize, SubSection, Symbol};
+use crate::util::{parse_hex, split_token, thumb_address};
+
+#[derive(Debug)]
+enum ParserState {
+    None,
+    Skipped,
+    PlacementSummary,
+    ModuleSummary,
+    EntryList,
+}
+
+/// Columns of the `Kind` and `Object` fields, taken from the placement table header.
+struct PlacementColumns {
+    kind: usize,
+    object: usize,
+}
+
+/// End columns of the right aligned `ro code`, `ro data` and `rw data` fields.
+struct ModuleColumns {
+    ro_code: usize,
+    ro_data: usize,
+    rw_data: usize,
+}
+
+/// Parse an IAR ILINK map file. Placement directives become memory regions, the
+/// placement summary blocks output sections and the module summary object sizes.
+pub fn parse<R: BufRead>(lines: &mut MapLines<R>) -> Result<MapFile, ParseError> {
+    let mut memory_configuration: Vec<MemoryConfig> = Vec::new();
+    let mut memory_map: Vec<MemoryMap> = Vec::new();
+    let mut symbols: Vec<(String, Symbol)> = Vec::new();
+    let mut object_sizes: Vec<ObjectSize> = Vec::new();
+    // `[1] = C:\proj\Debug\Obj` at the end of the file, objects refer to it as `main.o [1]`
+    let mut directories: HashMap<String, String> = HashMap::new();
+
+    let mut parser_state = ParserState::None;
+    // A placement directive may continue over several lines until its `;`
+    let mut directive = String::new();
+    let mut placement_columns: Option<PlacementColumns> = None;
+    let mut module_columns: Option<ModuleColumns> = None;
+    // The library whose modules are listed, `None` for object directories
+    let mut library: Option<String> = None;
+    // Names longer than their column are printed on a line of their own
+    let mut long_name: Option<String> = None;
+
+    while let Some(line) = lines.next_line()? {
+        let trimmed = line.trim();
+        // Headings are framed by lines of `*`
+        if trimmed.starts_with("***") {
+            if let Some(heading) = trimmed.strip_prefix("*** ") {
+                parser_state = match heading {
+                    "PLACEMENT SUMMARY" => ParserState::PlacementSummary,
+                    "MODULE SUMMARY" => ParserState::ModuleSummary,
+                    "ENTRY LIST" => ParserState::EntryList,
+                    _ => ParserState::Skipped,
+                };
+            }
+            continue;
+        }
+        if let Some((index, path)) = trimmed.strip_prefix('[').and_then(|text| text.split_once("] = ")) {
+            directories.insert(index.to_string(), path.to_string());
+            // The list of directories and libraries ends the entry list
+            parser_state = ParserState::Skipped;
+            continue;
+        }
+
+        if trimmed.is_empty() || trimmed.starts_with("---") {
+            continue;
+        }
+
+        match parser_state {
+            ParserState::PlacementSummary => match &placement_columns {
+                None if !directive.is_empty() || trimmed.starts_with('"') || is_directive(trimmed) => {
+                    directive.push(' ');
+                    directive.push_str(trimmed);
+                    if directive.ends_with(';') {
+                        parse_placement_directive(&directive, &mut memory_configuration);
+                        directive.clear();
+                    }
+                }
+                None => {
+                    if let (Some(kind), Some(object)) = (line.find("Kind"), line.find("Object")) {
+                        placement_columns = Some(PlacementColumns { kind, object });
+                    } else {
+                        lines.unrecognized()?;
+                    }
+                }
+                Some(_) if trimmed.starts_with("Unused ranges") => {
+                    parser_state = ParserState::Skipped;
+                }
+                Some(_) if trimmed.starts_with('"') => {
+                    // `"P1":  0x1fc` or `"P2", part 1 of 3:  0x8`
+                    let name = trimmed[1..].split('"').next().unwrap_or("");
+                    let length = trimmed.rsplit(' ').next().and_then(parse_number);
+                    memory_map.push(MemoryMap {
+                        section: name.to_string(),
+                        section_length: length,
+                        ..Default::default()
+                    });
+                }
+                Some(columns) => {
+                    // The last line of a block is `- end  size`
+                    if !trimmed.starts_with("- ") {
+                        parse_placement_line(&line, columns, &mut long_name, &mut memory_map)?;
+                    }
+                }
+            },
+            ParserState::ModuleSummary => {
+                if let (Some(ro_code), Some(ro_data), Some(rw_data)) =
+                    (line.find("ro code"), line.find("ro data"), line.find("rw data"))
+                {
+                    module_columns = Some(ModuleColumns {
+                        ro_code: ro_code + "ro code".len(),
+                        ro_data: ro_data + "ro data".len(),
+                        rw_data: rw_data + "rw data".len(),
+                    });
+                } else if !line.starts_with(' ') {
+                    // `m7M_tl.a: [3]`, `C:\proj\Debug\Obj: [1]` or `command line/config:`
+                    if !trimmed.ends_with(':') && !trimmed.contains(": [") {
+                        lines.unrecognized()?;
+                        continue;
+                    }
+                    let name = trimmed.split(": [").next().unwrap_or(trimmed);
+                    library = (name.ends_with(".a") || name.ends_with(".lib")).then(|| name.to_string());
+                } else if ["Total:", "Grand Total:", "Gaps", "Linker created"]
+                    .iter()
+                    .any(|prefix| trimmed.starts_with(prefix))
+                {
+                    // Totals are not read
+                } else if let Some(columns) = &module_columns
+                    && trimmed.rsplit(' ').next().and_then(parse_number).is_some()
+                {
+                    // `main.o  92  24  260`, a module row ends with a size
+                    object_sizes.push(parse_module_line(&line, columns, library.as_deref())?);
+                } else {
+                    lines.unrecognized()?;
+                }
+            }
+            ParserState::EntryList => {
+                if trimmed.starts_with("Entry ") {
+                    continue;
+                }
+                // The address follows the name, or starts the line after a long name
+                let address = match long_name {
+                    Some(_) => split_token(trimmed).0,
+                    None => split_token(split_token(trimmed).1).0,
+                };
+                if !address.is_empty() && !address.starts_with("0x") {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                if let Some(symbol) = parse_entry_line(trimmed, &mut long_name)? {
+                    symbols.push(symbol);
+                }
+            }
+            ParserState::None => {
+                // The banner before the first heading is framed by `#`
+                if !trimmed.starts_with('#') {
+                    lines.unrecognized()?;
+                }
+            }
+            ParserState::Skipped => {
+                // Sections which are not read, such as the runtime model attributes, the
+                // unused ranges and the memory totals at the end of the file
+            }
+        }
+    }
+
+    for sub_section in memory_map.iter_mut().flat_map(|m| m.sub_section.iter_mut()) {
+        sub_section.object = resolve_object(&sub_section.object, &directories);
+    }
+    for (object, symbol) in symbols {
+        let object = resolve_object(&object, &directories);
+        let sub_section = memory_map.iter_mut().flat_map(|m| m.sub_section.iter_mut()).find(|sub_section| {
+            sub_section.object == object
+                && matches!((sub_section.address, sub_section.length), (Some(address), Some(length))
+                    if (address..address + length).contains(&symbol.address))
+        });
+        if let Some(sub_section) = sub_section {
+            sub_section.symbols.push(symbol);
+        }
+    }
+
+    Ok(MapFile {
+        memory_configuration,
+        memory_map,
+        object_sizes,
+        ..Default::default()
+    })
+}
+
+/// Directives such as `initialize by copy { rw };` are listed with the placements.
+fn is_directive(line: &str) -> bool {
+    ["define ", "initialize ", "do not initialize ", "keep ", "place "]
+        .iter()
+        .any(|keyword| line.starts_with(keyword))
+}
+
+/// IAR separates groups of digits with `'`, as in `0x800'0000` and `2'048`.
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.replace('\'', "");
+    if text.starts_with("0x") {
+        parse_hex(&text)
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// `"P1": place in [from 0x800'0000 to 0x807'ffff] { ro };`, one region per address range
+fn parse_placement_directive(directive: &str, memory_configuration: &mut Vec<MemoryConfig>) {
+    let directive = directive.trim();
+    let Some((name, rest)) = directive.strip_prefix('"').and_then(|text| text.split_once('"')) else {
+        return;
+    };
+    let attribute = rest
+        .split_once('{')
+        .and_then(|(_, contents)| contents.split_once('}'))
+        .map(|(contents, _)| contents.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+
+    for range in rest.split("[from ").skip(1) {
+        let range = range.split(']').next().unwrap_or("");
+        let mut tokens = range.split_whitespace();
+        let (Some(start), Some(kind), Some(end)) = (tokens.next(), tokens.next(), tokens.next()) else {
+            continue;
+        };
+        let (Some(start), Some(end)) = (parse_number(start), parse_number(end)) else {
+            continue;
+        };
+        let length = match kind {
+            "to" => end.saturating_sub(start) + 1,
+            "size" => end,
+            _ => continue,
+        };
+        memory_configuration.push(MemoryConfig {
+            name: name.to_string(),
+            origin: start,
+            length,
+            attribute: attribute.clone(),
+            ..Default::default()
+        });
+    }
+}
+
+/// `Section  Kind  Address  Size  Object`
+fn parse_placement_line(
+    line: &str,
+    columns: &PlacementColumns,
+    long_name: &mut Option<String>,
+    memory_map: &mut [MemoryMap],
+) -> Result<(), ParseError> {
+    let Some(last_map) = memory_map.last_mut() else {
+        return Ok(());
+    };
+    let Some(address_start) = line.find(" 0x") else {
+        *long_name = Some(line.trim().to_string());
+        return Ok(());
+    };
+    let name = long_name
+        .take()
+        .unwrap_or_else(|| line.get(..columns.kind.min(address_start)).unwrap_or("").trim().to_string());
+    let kind = line.get(columns.kind..address_start).unwrap_or("").trim();
+
+    let (address, rest) = split_token(&line[address_start..]);
+    let address = parse_number(address).ok_or_else(|| ParseError::invalid_number(address))?;
+    let (size, _) = split_token(rest);
+    let size = parse_number(size).ok_or_else(|| ParseError::invalid_number(size))?;
+    let object = line.get(columns.object..).unwrap_or("").trim();
+
+    if last_map.section_address.is_none() {
+        last_map.section_address = Some(address);
+    }
+    // Blocks have no kind, the sections placed in them follow indented
+    if kind.is_empty() {
+        return Ok(());
+    }
+    last_map.sub_section.push(SubSection {
+        name: vec![name],
+        address: Some(address),
+        length: Some(size),
+        object: object.to_string(),
+        ..Default::default()
+    });
+    Ok(())
+}
+
+/// `Module  ro code  ro data  rw data`, where an empty column means zero
+fn parse_module_line(line: &str, columns: &ModuleColumns, library: Option<&str>) -> Result<ObjectSize, ParseError> {
+    // The module name may contain spaces and ends where the first size starts
+    let head = line.get(..columns.ro_code).unwrap_or(line).trim_end();
+    let head = match head.rsplit_once(' ') {
+        Some((module, size)) if parse_number(size).is_some() => module,
+        _ => head,
+    };
+    let module = head.trim();
+    let module_end = head.len();
+    let column = |start: usize, end: usize| -> Result<u64, ParseError> {
+        let text = line.get(start.min(line.len())..end.min(line.len())).unwrap_or("").trim();
+        if text.is_empty() {
+            return Ok(0);
+        }
+        parse_number(text).ok_or_else(|| ParseError::invalid_number(text))
+    };
+    let object = match library {
+        Some(library) => format!("{}({})", library, module),
+        None => module.to_string(),
+    };
+    Ok(ObjectSize {
+        object,
+        code: column(module_end, columns.ro_code)?,
+        ro_data: column(columns.ro_code, columns.ro_data)?,
+        rw_data: column(columns.ro_data, columns.rw_data)?,
+        ..Default::default()
+    })
+}
+
+/// `main  0x800'0041  0x5c  Code  Gb  main.o [1]`, returned with its object
+fn parse_entry_line(line: &str, long_name: &mut Option<String>) -> Result<Option<(String, Symbol)>, ParseError> {
+    let (name, rest) = match long_name.take() {
+        Some(name) => (name, line),
+        None => {
+            let (name, rest) = split_token(line);
+            if rest.is_empty() {
+                *long_name = Some(name.to_string());
+                return Ok(None);
+            }
+            (name.to_string(), rest)
+        }
+    };
+
+    let (address, rest) = split_token(rest);
+    let address = parse_number(address).ok_or_else(|| ParseError::invalid_number(address))?;
+    // The size is left empty for labels
+    let (size, rest) = match split_token(rest) {
+        (size, rest) if size.starts_with("0x") => {
+            (parse_number(size).ok_or_else(|| ParseError::invalid_number(size))?, rest)
+        }
+        _ => (0, rest),
+    };
+    let (kind, rest) = split_token(rest);
+    let (_scope, object) = split_token(rest);
+
+    let address = match kind {
+        // Thumb function addresses have bit 0 set
+        "Code" => thumb_address(address),
+        "Data" => address,
+        // Linker created labels (`--`) do not belong to a section
+        _ => return Ok(None),
+    };
+    Ok(Some((object.trim_end().to_string(), Symbol { address, name, size })))
+}
+
+/// Replace the `[1]` in `main.o [1]` by the library it refers to, giving `m7M_tl.a(printf.o)`,
+/// or drop it when it refers to an object directory.
+fn resolve_object(object: &str, directories: &HashMap<String, String>) -> String {
+    let Some((module, index)) = object.strip_suffix(']').and_then(|object| object.rsplit_once(" [")) else {
+        return object.to_string();
+    };
+    match directories.get(index) {
+        Some(library) if library.ends_with(".a") || library.ends_with(".lib") => format!("{}({})", library, module),
+        _ => module.to_string(),
+    }
+}
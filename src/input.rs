@@ -0,0 +1,34 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+
+use flate2::bufread::MultiGzDecoder;
+use xz2::bufread::XzDecoder;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Open the map file at `path`, or standard input for `-`. gzip, xz and zstd
+/// compressed maps are recognized by their magic bytes and decompressed.
+pub fn open(path: &str) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = if path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    };
+
+    // A pipe may return less than the magic bytes on the first read
+    let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+    (&mut reader).take(XZ_MAGIC.len() as u64).read_to_end(&mut magic)?;
+    let reader = io::Cursor::new(magic.clone()).chain(reader);
+
+    Ok(if magic.starts_with(&GZIP_MAGIC) {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if magic.starts_with(&XZ_MAGIC) {
+        Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader)))
+    } else if magic.starts_with(&ZSTD_MAGIC) {
+        Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        Box::new(reader)
+    })
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::lines::MapLines;
+use crate::model::{MapFile, MemoryMap, OutputFile, Symbol};
+use crate::util::{parse_hex, split_at_symbols, split_token};
+
+#[derive(Debug)]
+enum ParserState {
+    None,
+    ObjectFiles,
+    Sections,
+    Symbols,
+    DeadStrippedSymbols,
+}
+
+/// Parse an Apple ld64 map file (`-map`). Symbols refer to their object file by its
+/// `[  N]` index in the object file list. The map has no input sections, so the
+/// sections are split into input sections at the symbols.
+pub fn parse<R: BufRead>(lines: &mut MapLines<R>) -> Result<MapFile, ParseError> {
+    let mut objects: HashMap<usize, String> = HashMap::new();
+    let mut memory_map: Vec<MemoryMap> = Vec::new();
+    let mut symbols: Vec<(String, Symbol)> = Vec::new();
+    let mut path: Option<String> = None;
+    let mut arch = String::new();
+
+    let mut parser_state = ParserState::None;
+
+    while let Some(line) = lines.next_line()? {
+        if let Some(comment) = line.strip_prefix('#') {
+            let comment = comment.trim();
+            match comment {
+                "Object files:" => parser_state = ParserState::ObjectFiles,
+                "Sections:" => parser_state = ParserState::Sections,
+                "Symbols:" => parser_state = ParserState::Symbols,
+                "Dead Stripped Symbols:" => parser_state = ParserState::DeadStrippedSymbols,
+                _ => {
+                    if let Some(value) = comment.strip_prefix("Path:") {
+                        path = Some(value.trim().to_string());
+                    } else if let Some(value) = comment.strip_prefix("Arch:") {
+                        arch = value.trim().to_string();
+                    }
+                    // Other comments are column headers
+                }
+            }
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parser_state {
+            ParserState::ObjectFiles => {
+                // `[  1] /Users/dev/build/main.o`
+                if !line.starts_with('[') {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                let (index, object) = parse_file_reference(&line)?;
+                objects.insert(index, object.to_string());
+            }
+            ParserState::Sections => {
+                // `0x100003F00  0x000000A0  __TEXT  __text`
+                let (address, rest) = split_token(&line);
+                let (size, rest) = split_token(rest);
+                let (segment, section) = split_token(rest);
+                if !address.starts_with("0x") || section.trim().is_empty() {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                memory_map.push(MemoryMap {
+                    section: format!("{},{}", segment, section.trim()),
+                    section_address: Some(parse_number(address)?),
+                    section_length: Some(parse_number(size)?),
+                    ..Default::default()
+                });
+            }
+            ParserState::Symbols => {
+                // `0x100003F00  0x00000040  [  1] _main`, names may contain spaces
+                let (address, rest) = split_token(&line);
+                let (size, rest) = split_token(rest);
+                if !address.starts_with("0x") || !rest.starts_with('[') {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                let (index, name) = parse_file_reference(rest)?;
+                let object = objects.get(&index).cloned().unwrap_or_default();
+                symbols.push((
+                    object,
+                    Symbol {
+                        address: parse_number(address)?,
+                        name: name.to_string(),
+                        size: parse_number(size)?,
+                    },
+                ));
+            }
+            ParserState::DeadStrippedSymbols => {
+                // Symbols removed by `-dead_strip` are not read
+            }
+            ParserState::None => {
+                lines.unrecognized()?;
+            }
+        }
+    }
+
+    for memory_map in &mut memory_map {
+        let (Some(address), Some(length)) = (memory_map.section_address, memory_map.section_length) else {
+            continue;
+        };
+        let section_symbols = symbols
+            .iter()
+            .filter(|(_, symbol)| (address..address + length).contains(&symbol.address))
+            .cloned();
+        memory_map.sub_section = split_at_symbols(&memory_map.section, address, length, section_symbols);
+    }
+
+    Ok(MapFile {
+        memory_map,
+        output: path.map(|file| OutputFile { file, target: arch }),
+        ..Default::default()
+    })
+}
+
+fn parse_number(text: &str) -> Result<u64, ParseError> {
+    parse_hex(text).ok_or_else(|| ParseError::invalid_number(text))
+}
+
+/// Split `[  2] rest` into the file index and the rest.
+fn parse_file_reference(text: &str) -> Result<(usize, &str), ParseError> {
+    let (index, rest) = text
+        .trim_start()
+        .strip_prefix('[')
+        .and_then(|text| text.split_once(']'))
+        .ok_or_else(|| ParseError::invalid_number(text))?;
+    let index = index.trim();
+    let index = index.parse().map_err(|_| ParseError::invalid_number(index))?;
+    Ok((index, rest.trim()))
+}
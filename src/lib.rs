@@ -0,0 +1,213 @@
+//! Parser for linker map files.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! let file = File::open("test.map").unwrap();
+//! let map = mapa::parse(BufReader::new(file)).unwrap();
+//! for section in &map.memory_map {
+//!     println!("{} {:?}", section.section, section.section_length);
+//! }
+//! ```
+
+mod armlink;
+mod error;
+mod gnu;
+mod iar;
+mod ld64;
+mod lines;
+mod lld;
+mod model;
+mod msvc;
+mod ti;
+mod util;
+
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::str::FromStr;
+
+use lines::MapLines;
+
+pub use error::{ErrorKind, ParseError};
+pub use model::{
+    ArchiveMember, CommonSymbol, CrossReference, DiscardedSection, Fill, InputFile, MapFile, MemoryConfig, MemoryMap, ObjectSize, OutputFile, RegionUsage, SubSection, Symbol,
+    SymbolAssignment, Warning,
+};
+
+/// Linker map file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// GNU ld (`-Map`)
+    Gnu,
+    /// LLVM lld (`-Map`)
+    Lld,
+    /// GNU gold (`-Map`), laid out like GNU ld without the memory configuration
+    Gold,
+    /// mold (`-Map`), laid out like lld without load addresses
+    Mold,
+    /// Arm Compiler armlink (`--map --list`)
+    Armlink,
+    /// IAR ILINK (`--map`)
+    Iar,
+    /// TI linker for ARM, MSP430 and C2000 (`--map_file`)
+    Ti,
+    /// Microsoft link.exe (`/MAP`)
+    Msvc,
+    /// Apple ld64 (`-map`)
+    Ld64,
+}
+
+impl Format {
+    pub const ALL: [Format; 9] = [
+        Format::Gnu,
+        Format::Lld,
+        Format::Gold,
+        Format::Mold,
+        Format::Armlink,
+        Format::Iar,
+        Format::Ti,
+        Format::Msvc,
+        Format::Ld64,
+    ];
+
+    /// Guess the format of a map file from one of its first lines, by the headings and
+    /// banners each linker starts its map with.
+    pub fn detect(line: &str) -> Option<Format> {
+        const GNU_HEADINGS: [&str; 5] = [
+            "Archive member included to satisfy reference by file",
+            "Allocating common symbols",
+            "Discarded input sections",
+            "Memory Configuration",
+            "Linker script and memory map",
+        ];
+
+        let trimmed = line.trim();
+        let words: Vec<&str> = trimmed.split_whitespace().collect();
+        if trimmed.starts_with("Archive member included because of file") || trimmed == "Memory map" {
+            Some(Format::Gold)
+        } else if GNU_HEADINGS.iter().any(|heading| trimmed.starts_with(heading)) {
+            Some(Format::Gnu)
+        } else if trimmed.contains("Tool: armlink") || trimmed == "Image Symbol Table" || trimmed.starts_with("Memory Map of the image") {
+            Some(Format::Armlink)
+        } else if trimmed.contains("IAR ELF Linker") || trimmed == "*** PLACEMENT SUMMARY" {
+            Some(Format::Iar)
+        } else if trimmed.contains("Linker PC v") || trimmed.contains("Linker Unix v") || trimmed == "SECTION ALLOCATION MAP" {
+            Some(Format::Ti)
+        } else if trimmed.starts_with("Timestamp is ") || trimmed.starts_with("Preferred load address is ") {
+            Some(Format::Msvc)
+        } else if ["# Path:", "# Arch:", "# Object files:"].iter().any(|comment| trimmed.starts_with(comment)) {
+            Some(Format::Ld64)
+        } else if words.ends_with(&["Out", "In", "Symbol"]) {
+            // lld prints `VMA LMA Size Align`, or `Address Size Align` before lld 9, mold `VMA Size Align`
+            match (words.contains(&"VMA"), words.contains(&"LMA")) {
+                (true, false) => Some(Format::Mold),
+                _ => Some(Format::Lld),
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Gnu => "gnu",
+            Format::Lld => "lld",
+            Format::Gold => "gold",
+            Format::Mold => "mold",
+            Format::Armlink => "armlink",
+            Format::Iar => "iar",
+            Format::Ti => "ti",
+            Format::Msvc => "msvc",
+            Format::Ld64 => "ld64",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| {
+                let names = Format::ALL.map(Format::name).join(", ");
+                format!("unknown map format '{}', expected one of: {}", s, names)
+            })
+    }
+}
+
+/// Options for parsing a map file.
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    /// Fail on the first line which is not recognized, instead of collecting it in
+    /// `MapFile::warnings`.
+    pub strict: bool,
+}
+
+/// Parse a gnu map file from `reader`.
+pub fn parse<R: BufRead>(reader: R) -> Result<MapFile, ParseError> {
+    parse_format(reader, Format::Gnu)
+}
+
+/// Number of lines read to detect the format of a map file.
+const DETECT_LINES: usize = 64;
+
+/// Parse a map file from `reader`, detecting its format from the first lines.
+pub fn parse_detect<R: BufRead>(reader: R) -> Result<(Format, MapFile), ParseError> {
+    parse_detect_with(reader, &ParseOptions::default())
+}
+
+/// Parse a map file from `reader` with `options`, detecting its format from the first lines.
+pub fn parse_detect_with<R: BufRead>(mut reader: R, options: &ParseOptions) -> Result<(Format, MapFile), ParseError> {
+    let mut head = Vec::new();
+    let mut format = None;
+    for _ in 0..DETECT_LINES {
+        let start = head.len();
+        if reader.read_until(b'\n', &mut head)? == 0 {
+            break;
+        }
+        format = Format::detect(&String::from_utf8_lossy(&head[start..]));
+        if format.is_some() {
+            break;
+        }
+    }
+    let format = format.ok_or(ParseError::new(ErrorKind::UnknownFormat))?;
+
+    // The lines read to detect the format are parsed again
+    let map = parse_format_with(io::Cursor::new(head).chain(reader), format, options)?;
+    Ok((format, map))
+}
+
+/// Parse a map file written in `format` from `reader`.
+pub fn parse_format<R: BufRead>(reader: R, format: Format) -> Result<MapFile, ParseError> {
+    parse_format_with(reader, format, &ParseOptions::default())
+}
+
+/// Parse a map file written in `format` from `reader` with `options`.
+pub fn parse_format_with<R: BufRead>(reader: R, format: Format, options: &ParseOptions) -> Result<MapFile, ParseError> {
+    let mut lines = MapLines::new(reader, options);
+    let map = match format {
+        Format::Gnu | Format::Gold => gnu::parse(&mut lines),
+        Format::Lld | Format::Mold => lld::parse(&mut lines),
+        Format::Armlink => armlink::parse(&mut lines),
+        Format::Iar => iar::parse(&mut lines),
+        Format::Ti => ti::parse(&mut lines),
+        Format::Msvc => msvc::parse(&mut lines),
+        Format::Ld64 => ld64::parse(&mut lines),
+    }
+    .map_err(|e| lines.locate(e))?;
+    let (warnings, invalid_utf8_lines) = lines.into_warnings();
+    Ok(MapFile {
+        warnings,
+        invalid_utf8_lines,
+        ..map
+    })
+}
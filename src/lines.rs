@@ -0,0 +1,78 @@
+use std::io::BufRead;
+
+use crate::ParseOptions;
+use crate::error::{ErrorKind, ParseError};
+use crate::model::Warning;
+
+/// Reads a map file line by line, keeping the number and text of the current line
+/// for errors, and collecting the lines the parser does not recognize.
+pub(crate) struct MapLines<R> {
+    reader: R,
+    strict: bool,
+    number: usize,
+    current: String,
+    warnings: Vec<Warning>,
+    invalid_utf8_lines: Vec<usize>,
+}
+
+impl<R: BufRead> MapLines<R> {
+    pub(crate) fn new(reader: R, options: &ParseOptions) -> Self {
+        MapLines {
+            reader,
+            strict: options.strict,
+            number: 0,
+            current: String::new(),
+            warnings: Vec::new(),
+            invalid_utf8_lines: Vec::new(),
+        }
+    }
+
+    /// Read the next line without its line ending, `None` at the end of the file. Bytes
+    /// which are not valid UTF-8, as in Latin-1 paths, are replaced by U+FFFD.
+    pub(crate) fn next_line(&mut self) -> Result<Option<String>, ParseError> {
+        let mut bytes = Vec::new();
+        match self.reader.read_until(b'\n', &mut bytes) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {}
+            Err(e) => return Err(ParseError::from(e).at_line(self.number + 1, "")),
+        }
+        self.number += 1;
+        if bytes.ends_with(b"\n") {
+            bytes.pop();
+            if bytes.ends_with(b"\r") {
+                bytes.pop();
+            }
+        }
+        let line = match String::from_utf8(bytes) {
+            Ok(line) => line,
+            Err(e) => {
+                self.invalid_utf8_lines.push(self.number);
+                String::from_utf8_lossy(e.as_bytes()).into_owned()
+            }
+        };
+        self.current.clone_from(&line);
+        Ok(Some(line))
+    }
+
+    /// Report the current line as not recognized: an error in strict mode, a warning otherwise.
+    pub(crate) fn unrecognized(&mut self) -> Result<(), ParseError> {
+        if self.strict {
+            return Err(self.locate(ParseError::new(ErrorKind::UnrecognizedLine)));
+        }
+        self.warnings.push(Warning {
+            line: self.number,
+            text: self.current.clone(),
+        });
+        Ok(())
+    }
+
+    /// Attach the current line to an error raised while parsing it.
+    pub(crate) fn locate(&self, error: ParseError) -> ParseError {
+        error.at_line(self.number, &self.current)
+    }
+
+    /// The unrecognized lines and the numbers of the lines which were not valid UTF-8.
+    pub(crate) fn into_warnings(self) -> (Vec<Warning>, Vec<usize>) {
+        (self.warnings, self.invalid_utf8_lines)
+    }
+}
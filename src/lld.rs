@@ -0,0 +1,124 @@
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::lines::MapLines;
+use crate::model::{MapFile, MemoryMap, SubSection, Symbol, SymbolAssignment};
+use crate::util::{infer_symbol_sizes, parse_assignment_statement, parse_bare_hex, parse_hex};
+
+/// Columns of the `Out`, `In` and `Symbol` fields, taken from the header line.
+struct Columns {
+    out: usize,
+    input: usize,
+    symbol: usize,
+}
+
+/// Parse an LLVM lld map file, which prints one line per output section, input
+/// section and symbol below a `VMA LMA Size Align Out In Symbol` header.
+/// lld older than 9 prints `Address Size Align Out In Symbol` without a load address,
+/// as does mold, whose map uses the same layout.
+pub fn parse<R: BufRead>(lines: &mut MapLines<R>) -> Result<MapFile, ParseError> {
+    let mut memory_map: Vec<MemoryMap> = Vec::new();
+    let mut assignments = Vec::new();
+    let mut columns: Option<Columns> = None;
+
+    while let Some(line) = lines.next_line()? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(columns) = &columns else {
+            // The indentation of a line is measured from `Out`, so the columns must be in order
+            if let (Some(out), Some(input), Some(symbol)) = (line.find(" Out"), line.find(" In "), line.find(" Symbol"))
+                && out < input
+                && input < symbol
+            {
+                columns = Some(Columns {
+                    out: out + 1,
+                    input: input + 1,
+                    symbol: symbol + 1,
+                });
+            } else {
+                lines.unrecognized()?;
+            }
+            continue;
+        };
+
+        let Some(text) = line.get(columns.out..) else {
+            lines.unrecognized()?;
+            continue;
+        };
+        let numbers = line[..columns.out].split_whitespace().collect::<Vec<_>>();
+        let (address, load_address, size) = match numbers.as_slice() {
+            [vma, lma, size, _align] => (parse_bare_hex(vma)?, parse_bare_hex(lma)?, parse_bare_hex(size)?),
+            [vma, size, _align] => (parse_bare_hex(vma)?, parse_bare_hex(vma)?, parse_bare_hex(size)?),
+            _ => {
+                lines.unrecognized()?;
+                continue;
+            }
+        };
+
+        let indent = text.len() - text.trim_start().len();
+        let text = text.trim();
+        if indent >= columns.symbol - columns.out {
+            // Symbol or linker script assignment inside an output section
+            let Some(last_map) = memory_map.last_mut() else {
+                continue;
+            };
+            if let Some(assignment) = parse_assignment(address, text) {
+                last_map.assignments.push(assignment);
+            } else if let Some(last_sub_section) = last_map.sub_section.last_mut() {
+                last_sub_section.symbols.push(Symbol {
+                    address,
+                    name: text.to_string(),
+                    size: 0,
+                });
+            }
+        } else if indent >= columns.input - columns.out {
+            // Input section printed as `file:(section)`
+            let (object, section) = text
+                .rsplit_once(":(")
+                .map(|(object, section)| (object, section.strip_suffix(')').unwrap_or(section)))
+                .unwrap_or(("", text));
+            if let Some(last_map) = memory_map.last_mut() {
+                last_map.sub_section.push(SubSection {
+                    name: vec![section.to_string()],
+                    address: Some(address),
+                    length: Some(size),
+                    object: object.to_string(),
+                    ..Default::default()
+                });
+            }
+        } else if let Some(assignment) = parse_assignment(address, text) {
+            assignments.push(assignment);
+        } else {
+            memory_map.push(MemoryMap {
+                section: text.to_string(),
+                section_address: Some(address),
+                section_length: Some(size),
+                load_address: (load_address != address).then_some(load_address),
+                ..Default::default()
+            });
+        }
+    }
+
+    for sub_section in memory_map.iter_mut().flat_map(|m| m.sub_section.iter_mut()) {
+        infer_symbol_sizes(sub_section);
+    }
+
+    Ok(MapFile {
+        memory_map,
+        assignments,
+        ..Default::default()
+    })
+}
+
+/// lld prints the location counter where an assignment runs, not the value assigned,
+/// which is only known for `symbol = .` and for numbers; other expressions are not evaluated.
+fn parse_assignment(location: u64, text: &str) -> Option<SymbolAssignment> {
+    let mut assignment = parse_assignment_statement(None, text)?;
+    assignment.address = match assignment.expression.as_str() {
+        "." => Some(location),
+        expression => parse_hex(expression).or_else(|| expression.parse().ok()),
+    };
+    Some(assignment)
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+
+/// A line of the map file which the parser did not recognize.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    /// Line number, starting at 1.
+    pub line: usize,
+    pub text: String,
+}
+
+/// Everything extracted from a map file.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MapFile {
+    pub archive_members: Vec<ArchiveMember>,
+    pub common_symbols: Vec<CommonSymbol>,
+    pub discarded_sections: Vec<DiscardedSection>,
+    pub memory_configuration: Vec<MemoryConfig>,
+    pub memory_map: Vec<MemoryMap>,
+    pub cross_references: Vec<CrossReference>,
+    /// Files loaded by the linker, in link order.
+    pub input_files: Vec<InputFile>,
+    pub output: Option<OutputFile>,
+    /// Linker script assignments outside of any output section.
+    pub assignments: Vec<SymbolAssignment>,
+    /// Code and data sizes of each object, from armlink's image component sizes or
+    /// the IAR module summary.
+    pub object_sizes: Vec<ObjectSize>,
+    /// Bytes per address on targets whose memories are not byte addressed, 2 for C2000
+    /// which addresses 16-bit words. Addresses are then in words, lengths and sizes in
+    /// bytes. `None` for byte addressed targets.
+    pub address_unit: Option<u64>,
+    /// Lines which were not recognized, when not parsing in strict mode.
+    #[serde(skip)]
+    pub warnings: Vec<Warning>,
+    /// Numbers of the lines which were not valid UTF-8, read with the invalid bytes
+    /// replaced by U+FFFD.
+    #[serde(skip)]
+    pub invalid_utf8_lines: Vec<usize>,
+}
+
+impl MapFile {
+    /// True when nothing was read from the map file, as when it was parsed in the wrong format.
+    pub fn is_empty(&self) -> bool {
+        self.archive_members.is_empty()
+            && self.common_symbols.is_empty()
+            && self.discarded_sections.is_empty()
+            && self.memory_configuration.is_empty()
+            && self.memory_map.is_empty()
+            && self.cross_references.is_empty()
+            && self.input_files.is_empty()
+            && self.output.is_none()
+            && self.assignments.is_empty()
+            && self.object_sizes.is_empty()
+    }
+
+    /// Find an archive member by `archive(member)` or by member name alone.
+    pub fn archive_member(&self, name: &str) -> Option<&ArchiveMember> {
+        self.archive_members
+            .iter()
+            .find(|archive_member| archive_member.path() == name)
+            .or_else(|| self.archive_members.iter().find(|archive_member| archive_member.member == name))
+    }
+
+    /// Explain why `name` is linked by following the "included to satisfy reference by"
+    /// chain back to an object which is not an archive member. The chain starts with the
+    /// member pulled in by that object and ends with `name`.
+    pub fn why_linked(&self, name: &str) -> Option<Vec<&ArchiveMember>> {
+        let mut chain = vec![self.archive_member(name)?];
+        while let Some(referrer) = chain.last().and_then(|last| {
+            self.archive_members
+                .iter()
+                .find(|archive_member| archive_member.path() == last.referenced_by)
+        }) {
+            // The map should never contain a loop, but do not hang on a broken one
+            if chain.iter().any(|archive_member| archive_member.path() == referrer.path()) {
+                break;
+            }
+            chain.push(referrer);
+        }
+        chain.reverse();
+        Some(chain)
+    }
+
+    /// Total size of the discarded input sections of each object, largest first.
+    pub fn discarded_size_by_object(&self) -> Vec<(String, u64)> {
+        let mut sizes: Vec<(String, u64)> = Vec::new();
+        for discarded_section in &self.discarded_sections {
+            match sizes.iter_mut().find(|(object, _)| *object == discarded_section.object) {
+                Some((_, size)) => *size += discarded_section.size,
+                None => sizes.push((discarded_section.object.clone(), discarded_section.size)),
+            }
+        }
+        sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        sizes
+    }
+
+    /// Bytes used in each memory region. An output section with a load address is
+    /// counted against both the region it runs from and the region it is loaded from,
+    /// unless it only holds zero initialized data, which takes no space where it is loaded.
+    /// Sections which are not loaded into memory, such as `.comment` and `.debug_info`,
+    /// are listed at address 0 and not counted. On paged targets such as C2000, sections
+    /// are only counted against regions of their own page.
+    pub fn region_usage(&self) -> Vec<RegionUsage> {
+        let unit = self.address_unit.unwrap_or(1);
+        let mut usage: Vec<RegionUsage> = self
+            .memory_configuration
+            .iter()
+            .map(|memory| RegionUsage {
+                name: memory.name.clone(),
+                page: memory.page,
+                origin: memory.origin,
+                length: memory.length,
+                used: 0,
+            })
+            .collect();
+
+        for memory_map in &self.memory_map {
+            let (Some(address), Some(length)) = (memory_map.section_address, memory_map.section_length) else {
+                continue;
+            };
+            if address == 0 && is_unallocated(&memory_map.section) {
+                continue;
+            }
+            let load = memory_map
+                .load_address
+                .filter(|&lma| lma != address && !is_zero_initialized(memory_map))
+                .map(|lma| (lma, memory_map.load_length.unwrap_or(length)));
+            for (address, length) in std::iter::once((address, length)).chain(load) {
+                if let Some(region) = usage
+                    .iter_mut()
+                    .find(|region| region.page == memory_map.page && region.contains(address, unit))
+                {
+                    region.used += length;
+                }
+            }
+        }
+        usage
+    }
+
+    /// Look up a symbol in the cross reference table.
+    pub fn cross_reference(&self, symbol: &str) -> Option<&CrossReference> {
+        self.cross_references.iter().find(|cross_reference| cross_reference.symbol == symbol)
+    }
+}
+
+/// GNU ld prints a load address for `.bss` placed after an `AT> FLASH` section, although
+/// nothing of it is loaded. A section without input sections, such as a heap reserved by
+/// moving the location counter, has nothing to load either.
+fn is_zero_initialized(memory_map: &MemoryMap) -> bool {
+    memory_map
+        .sub_section
+        .iter()
+        .flat_map(|sub_section| &sub_section.name)
+        .all(|name| name.starts_with(".bss") || name == "COMMON" || name.starts_with(".noinit"))
+}
+
+/// Debug, comment and attribute sections, which the linker does not allocate memory for.
+fn is_unallocated(section: &str) -> bool {
+    const SECTIONS: [&str; 6] = [".comment", ".ident", ".line", ".ARM.attributes", ".riscv.attributes", ".gnu.attributes"];
+    const PREFIXES: [&str; 4] = [".debug", ".zdebug", ".stab", ".GCC.command.line"];
+    SECTIONS.contains(&section) || PREFIXES.iter().any(|prefix| section.starts_with(prefix))
+}
+
+/// An input section removed by the linker, e.g. by `--gc-sections`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DiscardedSection {
+    pub section: String,
+    pub address: u64,
+    pub size: u64,
+    pub object: String,
+}
+
+/// Bytes used in a memory region, see [`MapFile::region_usage`].
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RegionUsage {
+    pub name: String,
+    pub page: Option<u32>,
+    pub origin: u64,
+    pub length: u64,
+    pub used: u64,
+}
+
+impl RegionUsage {
+    /// True when `address`, in units of `unit` bytes, is inside the region.
+    pub fn contains(&self, address: u64, unit: u64) -> bool {
+        address >= self.origin && (address - self.origin).saturating_mul(unit) < self.length
+    }
+}
+
+/// An archive member included to satisfy a reference.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ArchiveMember {
+    pub archive: String,
+    pub member: String,
+    /// File whose reference caused the member to be included, may itself be an archive member.
+    pub referenced_by: String,
+    pub symbol: String,
+}
+
+impl ArchiveMember {
+    /// The member as the linker prints it, `archive(member)`.
+    pub fn path(&self) -> String {
+        format!("{}({})", self.archive, self.member)
+    }
+}
+
+/// Code and data contributed by an object or library member, in bytes.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ObjectSize {
+    pub object: String,
+    pub code: u64,
+    /// Inline data included in `code`, such as literal pools.
+    pub inc_data: u64,
+    pub ro_data: u64,
+    pub rw_data: u64,
+    pub zi_data: u64,
+    pub debug: u64,
+}
+
+/// A common (tentative) symbol allocated by the linker.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CommonSymbol {
+    pub symbol: String,
+    pub size: u64,
+    pub file: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    pub name: String,
+    /// Address space of the region on paged targets, `PAGE 0` for program and `PAGE 1`
+    /// for data memory on C2000.
+    pub page: Option<u32>,
+    pub origin: u64,
+    pub length: u64,
+    pub attribute: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SubSection {
+    pub name: Vec<String>,
+    pub address: Option<u64>,
+    pub length: Option<u64>,
+    pub object: String,
+    pub symbols: Vec<Symbol>,
+}
+
+/// A symbol defined inside an input section.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Symbol {
+    pub address: u64,
+    pub name: String,
+    /// Inferred from the next symbol address or the end of the input section.
+    pub size: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MemoryMap {
+    pub section: String,
+    /// Address space of the section on paged targets, see [`MemoryConfig::page`].
+    pub page: Option<u32>,
+    pub section_address: Option<u64>,
+    pub section_length: Option<u64>,
+    /// Load address (LMA) when it differs from the run address (VMA).
+    pub load_address: Option<u64>,
+    /// Bytes stored at the load address when fewer than `section_length`, e.g. an
+    /// armlink execution region ending in zero initialized data.
+    pub load_length: Option<u64>,
+    pub sub_section: Vec<SubSection>,
+    pub fills: Vec<Fill>,
+    pub assignments: Vec<SymbolAssignment>,
+}
+
+impl MemoryMap {
+    /// Bytes of the output section spent on `*fill*` padding.
+    pub fn fill_size(&self) -> u64 {
+        self.fills.iter().map(|fill| fill.size).sum()
+    }
+}
+
+/// Padding inserted by the linker, printed as `*fill*`, or data written by a linker
+/// script statement such as `LONG 0x12345678`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub address: u64,
+    pub size: u64,
+    /// Fill pattern or data value in hex, `None` for the default fill.
+    pub pattern: Option<String>,
+}
+
+/// A linker script assignment such as `_sdata = .` or `PROVIDE (end = .)`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SymbolAssignment {
+    /// Resolved value, `None` for a `PROVIDE` which was not needed, or when the map
+    /// does not give it: lld only prints the location counter of an assignment.
+    pub address: Option<u64>,
+    pub symbol: String,
+    pub expression: String,
+    /// The assignment is a `PROVIDE` or `PROVIDE_HIDDEN` statement.
+    pub provided: bool,
+}
+
+/// An entry of the cross reference table printed with `--cref`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CrossReference {
+    pub symbol: String,
+    /// The first file listed, which is the one defining the symbol.
+    pub defined_in: String,
+    pub referenced_by: Vec<String>,
+}
+
+/// A file loaded by the linker (`LOAD` record).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InputFile {
+    pub path: String,
+    /// Index of the `START GROUP`/`END GROUP` library group containing the file.
+    pub group: Option<usize>,
+}
+
+/// The linked output file and its BFD target (`OUTPUT` record).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct OutputFile {
+    pub file: String,
+    pub target: String,
+}
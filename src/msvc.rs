@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::lines::MapLines;
+use crate::model::{MapFile, MemoryMap, SubSection, Symbol};
+use crate::util::{infer_symbol_sizes, parse_bare_hex, split_at_symbols, split_token};
+
+#[derive(Debug)]
+enum ParserState {
+    Header,
+    None,
+    Groups,
+    Publics,
+    Exports,
+}
+
+/// `0001:00000000 00000180H .text$mn  CODE`
+struct Group {
+    segment: u32,
+    offset: u64,
+    length: u64,
+    name: String,
+}
+
+/// `0001:00000000  main  0000000140001000 f  main.obj`
+struct Public {
+    segment: u32,
+    offset: u64,
+    address: u64,
+    name: String,
+    object: String,
+}
+
+/// Parse an MSVC `link.exe /MAP` map file. The map lists section groups and public
+/// symbols but not the contribution of each object, so the groups are split into
+/// input sections at the symbols.
+pub fn parse<R: BufRead>(lines: &mut MapLines<R>) -> Result<MapFile, ParseError> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut publics: Vec<Public> = Vec::new();
+
+    let mut parser_state = ParserState::Header;
+    let mut module_name_read = false;
+
+    while let Some(line) = lines.next_line()? {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Start ") && trimmed.contains("Class") {
+            parser_state = ParserState::Groups;
+            continue;
+        } else if trimmed.starts_with("Address ") && trimmed.contains("Publics by Value") {
+            parser_state = ParserState::Publics;
+            continue;
+        } else if trimmed == "Static symbols" {
+            // Static symbols are listed like the publics
+            parser_state = ParserState::Publics;
+            continue;
+        } else if trimmed.starts_with("entry point at") {
+            parser_state = ParserState::None;
+            continue;
+        } else if trimmed == "Exports" {
+            parser_state = ParserState::Exports;
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        match parser_state {
+            ParserState::Groups => {
+                let (address, rest) = split_token(trimmed);
+                let (length, rest) = split_token(rest);
+                let (name, _class) = split_token(rest);
+                if !is_address(address) || !length.ends_with('H') || name.is_empty() {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                let (segment, offset) = parse_address(address)?;
+                groups.push(Group {
+                    segment,
+                    offset,
+                    length: parse_bare_hex(length.strip_suffix('H').unwrap_or(length))?,
+                    name: name.to_string(),
+                });
+            }
+            ParserState::Publics => {
+                let (address, rest) = split_token(trimmed);
+                let (name, rest) = split_token(rest);
+                let (rva_base, rest) = split_token(rest);
+                if !is_address(address) || rva_base.is_empty() {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                let (segment, offset) = parse_address(address)?;
+                // `f` marks functions and `i` inline functions, before the object
+                let mut object = rest;
+                while let ("f" | "i", rest) = split_token(object) {
+                    object = rest;
+                }
+                publics.push(Public {
+                    segment,
+                    offset,
+                    address: parse_bare_hex(rva_base)?,
+                    name: name.to_string(),
+                    object: object_path(object.trim_end()),
+                });
+            }
+            ParserState::Header => {
+                // The module name on the first line, its timestamp and preferred load address
+                if module_name_read
+                    && !trimmed.starts_with("Timestamp is ")
+                    && !trimmed.starts_with("Preferred load address is ")
+                {
+                    lines.unrecognized()?;
+                }
+                module_name_read = true;
+            }
+            ParserState::Exports => {
+                // The exported ordinals and names are not read
+            }
+            ParserState::None => lines.unrecognized()?,
+        }
+    }
+
+    // Segment 0 holds absolute symbols such as `__ImageBase`
+    publics.retain(|public| public.segment != 0);
+    publics.sort_by_key(|public| (public.segment, public.offset));
+
+    let mut memory_map = build_memory_map(&groups, &publics);
+    for sub_section in memory_map.iter_mut().flat_map(|m| m.sub_section.iter_mut()) {
+        infer_symbol_sizes(sub_section);
+    }
+
+    Ok(MapFile {
+        memory_map,
+        ..Default::default()
+    })
+}
+
+/// Whether `text` has the `0001:00000040` layout of an address, before its numbers are parsed.
+fn is_address(text: &str) -> bool {
+    matches!(text.split_once(':'), Some((segment, offset)) if !segment.is_empty() && !offset.is_empty())
+}
+
+/// `0001:00000040` as the section number and the offset in the section.
+fn parse_address(text: &str) -> Result<(u32, u64), ParseError> {
+    let (segment, offset) = text
+        .split_once(':')
+        .ok_or_else(|| ParseError::invalid_number(text))?;
+    let segment = u32::from_str_radix(segment, 16).map_err(|_| ParseError::invalid_number(segment))?;
+    Ok((segment, parse_bare_hex(offset)?))
+}
+
+/// `libcmt:printf.obj` is the member `printf.obj` of the library `libcmt`.
+fn object_path(object: &str) -> String {
+    // The colon of a drive letter, as in `C:\My Project\main.obj`, is part of the path
+    match object.rsplit_once(':') {
+        Some((library, member)) if !library.is_empty() && !member.starts_with(['\\', '/']) => {
+            format!("{}({})", library, member)
+        }
+        _ => object.to_string(),
+    }
+}
+
+/// One output section per section number, named after its groups (`.text$mn` is part
+/// of `.text`). The section address is only known when one of its symbols gives it.
+fn build_memory_map(groups: &[Group], publics: &[Public]) -> Vec<MemoryMap> {
+    let mut bases: HashMap<u32, u64> = HashMap::new();
+    for public in publics {
+        bases.entry(public.segment).or_insert(public.address.wrapping_sub(public.offset));
+    }
+
+    let mut memory_map: Vec<MemoryMap> = Vec::new();
+    let mut segment: Option<u32> = None;
+    for group in groups {
+        let base = bases.get(&group.segment).copied();
+        if segment != Some(group.segment) {
+            segment = Some(group.segment);
+            let segment_groups = groups.iter().filter(|other| other.segment == group.segment);
+            let name = segment_groups
+                .clone()
+                .find(|other| !other.name.contains('$'))
+                .map(|other| other.name.as_str())
+                .unwrap_or_else(|| group.name.split('$').next().unwrap_or(&group.name));
+            let end = segment_groups.map(|other| other.offset + other.length).max().unwrap_or(0);
+            memory_map.push(MemoryMap {
+                section: name.to_string(),
+                section_address: base.map(|base| base + group.offset),
+                section_length: Some(end - group.offset),
+                ..Default::default()
+            });
+        }
+        let Some(last_map) = memory_map.last_mut() else {
+            continue;
+        };
+
+        let sub_sections = match base {
+            Some(base) => {
+                let group_publics = publics.iter().filter(|public| {
+                    public.segment == group.segment
+                        && (group.offset..group.offset + group.length).contains(&public.offset)
+                });
+                let symbols = group_publics.map(|public| {
+                    let symbol = Symbol {
+                        address: public.address,
+                        name: public.name.clone(),
+                        size: 0,
+                    };
+                    (public.object.clone(), symbol)
+                });
+                split_at_symbols(&group.name, base + group.offset, group.length, symbols)
+            }
+            // No symbol in the section gives its address
+            None => vec![SubSection {
+                name: vec![group.name.clone()],
+                length: Some(group.length),
+                ..Default::default()
+            }],
+        };
+        last_map.sub_section.extend(sub_sections);
+    }
+    memory_map
+}
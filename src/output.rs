@@ -0,0 +1,285 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use mapa::MapFile;
+
+fn create_file(out_path: &str, file_name: &str) -> io::Result<(PathBuf, File)> {
+    let output_file_path = Path::new(out_path).join(file_name);
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&output_file_path)?;
+    Ok((output_file_path, file))
+}
+
+fn address(value: Option<u64>) -> String {
+    value.map(|v| format!("{:#010x}", v)).unwrap_or_default()
+}
+
+fn hex(value: Option<u64>) -> String {
+    value.map(|v| format!("{:#x}", v)).unwrap_or_default()
+}
+
+fn decimal(value: Option<u64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn write_json<T: serde::Serialize>(out_path: &str, file_name: &str, value: &T) -> io::Result<PathBuf> {
+    let json = serde_json::to_string(value)?;
+    let (output_file_path, mut file) = create_file(out_path, file_name)?;
+    writeln!(file, "{}", json)?;
+    Ok(output_file_path)
+}
+
+/// Save the parsed map to `.tsv` or `.json` files in `out_path`.
+pub fn write_map_file(map: &MapFile, out_path: &str, json: bool) -> io::Result<()> {
+    if !map.archive_members.is_empty() {
+        if json {
+            let output_file_path = write_json(out_path, "archive_members.json", &map.archive_members)?;
+            println!("Archive Members saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "archive_members.tsv")?;
+            for member in &map.archive_members {
+                writeln!(file, "{}\t{}\t{}\t{}", member.archive, member.member, member.referenced_by, member.symbol)?;
+            }
+            println!("Archive Members saved to {}", output_file_path.display());
+        }
+    }
+
+    if !map.common_symbols.is_empty() {
+        if json {
+            let output_file_path = write_json(out_path, "common_symbols.json", &map.common_symbols)?;
+            println!("Common Symbols saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "common_symbols.tsv")?;
+            for common_symbol in &map.common_symbols {
+                writeln!(
+                    file,
+                    "{}\t{:#x}\t{}\t{}",
+                    common_symbol.symbol, common_symbol.size, common_symbol.size, common_symbol.file
+                )?;
+            }
+            println!("Common Symbols saved to {}", output_file_path.display());
+        }
+    }
+
+    if !map.discarded_sections.is_empty() {
+        if json {
+            let output_file_path = write_json(out_path, "discarded_sections.json", &map.discarded_sections)?;
+            println!("Discarded Sections to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "discarded_sections.tsv")?;
+            for section in &map.discarded_sections {
+                writeln!(
+                    file,
+                    "{}\t{:#010x}\t{}\t{:#x}\t{}\t{}",
+                    section.section, section.address, section.address, section.size, section.size, section.object
+                )?;
+            }
+            println!("Discarded Sections to {}", output_file_path.display());
+        }
+    }
+
+    if !map.memory_configuration.is_empty() {
+        println!("\nMemory Configuration:");
+        if json {
+            let output_file_path = write_json(out_path, "memory_configuration.json", &map.memory_configuration)?;
+            println!("Memory configuration saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "memory_configuration.tsv")?;
+            for memory in &map.memory_configuration {
+                writeln!(
+                    file,
+                    "{}\t{:#010x}\t{}\t{:#x}\t{}\t{}",
+                    memory.name, memory.origin, memory.origin, memory.length, memory.length, memory.attribute
+                )?;
+            }
+            println!("Memory configuration saved to {}", output_file_path.display());
+        }
+
+        let region_usage = map.region_usage();
+        if json {
+            let output_file_path = write_json(out_path, "region_usage.json", &region_usage)?;
+            println!("Region usage saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "region_usage.tsv")?;
+            for region in &region_usage {
+                writeln!(
+                    file,
+                    "{}\t{:#010x}\t{}\t{:#x}\t{}\t{:#x}\t{}",
+                    region.name, region.origin, region.origin, region.length, region.length, region.used, region.used
+                )?;
+            }
+            println!("Region usage saved to {}", output_file_path.display());
+        }
+        for region in &region_usage {
+            println!("{}: {} of {} bytes used", region.name, region.used, region.length);
+        }
+    }
+
+    if !map.object_sizes.is_empty() {
+        if json {
+            let output_file_path = write_json(out_path, "object_sizes.json", &map.object_sizes)?;
+            println!("Object sizes saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "object_sizes.tsv")?;
+            for size in &map.object_sizes {
+                writeln!(
+                    file,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    size.object, size.code, size.inc_data, size.ro_data, size.rw_data, size.zi_data, size.debug
+                )?;
+            }
+            println!("Object sizes saved to {}", output_file_path.display());
+        }
+    }
+
+    if !map.input_files.is_empty() {
+        if json {
+            let output_file_path = write_json(out_path, "input_files.json", &map.input_files)?;
+            println!("Input Files saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "input_files.tsv")?;
+            for input_file in &map.input_files {
+                let group = input_file.group.map(|group| group.to_string()).unwrap_or_default();
+                writeln!(file, "{}\t{}", input_file.path, group)?;
+            }
+            println!("Input Files saved to {}", output_file_path.display());
+        }
+    }
+
+    if let Some(output) = &map.output {
+        println!("Output: {} ({})", output.file, output.target);
+    }
+
+    if !map.memory_map.is_empty() {
+        println!("\nLinker Script and Memory Map:");
+        if json {
+            let output_file_path = write_json(out_path, "linker_script_memory_map.json", &map.memory_map)?;
+            println!("Linker Script and Memory Map saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "memory_map.tsv")?;
+            for memory_map in &map.memory_map {
+                for sub in &memory_map.sub_section {
+                    writeln!(
+                        file,
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        memory_map.section, address(memory_map.load_address), sub.name.join(" "),
+                        address(sub.address), decimal(sub.address), hex(sub.length), decimal(sub.length),
+                        sub.object, sub.symbols.iter().map(|symbol| symbol.name.as_str()).collect::<Vec<_>>().join(" ")
+                    )?;
+                }
+            }
+            println!("Linker Script and Memory Map saved to {}", output_file_path.display());
+
+            let (output_file_path, mut file) = create_file(out_path, "symbols.tsv")?;
+            for memory_map in &map.memory_map {
+                for sub in &memory_map.sub_section {
+                    for symbol in &sub.symbols {
+                        writeln!(
+                            file,
+                            "{}\t{}\t{}\t{:#010x}\t{}\t{:#x}\t{}\t{}",
+                            memory_map.section, sub.name.join(" "), sub.object,
+                            symbol.address, symbol.address, symbol.size, symbol.size, symbol.name
+                        )?;
+                    }
+                }
+            }
+            println!("Symbols saved to {}", output_file_path.display());
+
+            let (output_file_path, mut file) = create_file(out_path, "fills.tsv")?;
+            for memory_map in &map.memory_map {
+                for fill in &memory_map.fills {
+                    writeln!(
+                        file,
+                        "{}\t{:#010x}\t{}\t{:#x}\t{}\t{}",
+                        memory_map.section, fill.address, fill.address, fill.size, fill.size,
+                        fill.pattern.as_deref().unwrap_or("")
+                    )?;
+                }
+            }
+            println!("Fills saved to {}", output_file_path.display());
+        }
+
+        for memory_map in map.memory_map.iter().filter(|memory_map| !memory_map.fills.is_empty()) {
+            println!("{}: {} bytes of fill", memory_map.section, memory_map.fill_size());
+        }
+    }
+
+    let has_section_assignments = map.memory_map.iter().any(|memory_map| !memory_map.assignments.is_empty());
+    if json && !map.assignments.is_empty() {
+        let output_file_path = write_json(out_path, "assignments.json", &map.assignments)?;
+        println!("Assignments saved to {}", output_file_path.display());
+    } else if !json && (has_section_assignments || !map.assignments.is_empty()) {
+        let (output_file_path, mut file) = create_file(out_path, "assignments.tsv")?;
+        let global = map.assignments.iter().map(|assignment| ("", assignment));
+        let sections = map.memory_map.iter().flat_map(|memory_map| {
+            memory_map.assignments.iter().map(|assignment| (memory_map.section.as_str(), assignment))
+        });
+        for (section, assignment) in global.chain(sections) {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                section, address(assignment.address), decimal(assignment.address),
+                assignment.symbol, assignment.expression, assignment.provided
+            )?;
+        }
+        println!("Assignments saved to {}", output_file_path.display());
+    }
+
+    if !map.cross_references.is_empty() {
+        println!("\nCross Reference Table:");
+        if json {
+            let output_file_path = write_json(out_path, "cross_reference.json", &map.cross_references)?;
+            println!("Cross Reference Table saved to {}", output_file_path.display());
+        } else {
+            let (output_file_path, mut file) = create_file(out_path, "cross_reference.tsv")?;
+            for cross_reference in &map.cross_references {
+                if cross_reference.referenced_by.is_empty() {
+                    writeln!(file, "{}\t{}\t", cross_reference.symbol, cross_reference.defined_in)?;
+                }
+                for referrer in &cross_reference.referenced_by {
+                    writeln!(file, "{}\t{}\t{}", cross_reference.symbol, cross_reference.defined_in, referrer)?;
+                }
+            }
+            println!("Cross Reference Table saved to {}", output_file_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Print where `symbol` is defined and which files reference it.
+pub fn print_cross_reference(map: &MapFile, symbol: &str) {
+    match map.cross_reference(symbol) {
+        Some(cross_reference) => {
+            println!("\n{}", cross_reference.symbol);
+            println!("  defined in: {}", cross_reference.defined_in);
+            for referrer in &cross_reference.referenced_by {
+                println!("  referenced by: {}", referrer);
+            }
+        }
+        None if map.cross_references.is_empty() => {
+            println!("\nNo cross reference table in map file, link with --cref to generate it");
+        }
+        None => println!("\nSymbol '{}' not found in cross reference table", symbol),
+    }
+}
+
+/// Print the chain of references which caused `name` to be linked.
+pub fn print_why_linked(map: &MapFile, name: &str) {
+    match map.why_linked(name) {
+        Some(chain) => {
+            println!("\n{} is linked because:", name);
+            for archive_member in chain {
+                println!(
+                    "  {} references {} -> {}",
+                    archive_member.referenced_by, archive_member.symbol, archive_member.path()
+                );
+            }
+        }
+        None => println!("\n'{}' is not an archive member included to satisfy a reference", name),
+    }
+}
@@ -0,0 +1,301 @@
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::lines::MapLines;
+use crate::model::{Fill, MapFile, MemoryConfig, MemoryMap, SubSection, Symbol};
+use crate::util::{infer_word_symbol_sizes, parse_bare_hex, split_token, thumb_address};
+
+#[derive(Debug)]
+enum ParserState {
+    None,
+    Skipped,
+    MemoryConfiguration,
+    SectionAllocationMap,
+    GlobalSymbols,
+}
+
+/// Parse a TI linker (armcl, cl430, cl2000) map file. C2000 memories are addressed in
+/// 16-bit words; their addresses are kept as word addresses, as the debugger shows
+/// them, and their lengths converted to bytes so that sizes compare with other targets.
+/// C2000 program (`PAGE 0`) and data (`PAGE 1`) memories are separate address spaces.
+pub fn parse<R: BufRead>(lines: &mut MapLines<R>) -> Result<MapFile, ParseError> {
+    let mut memory_configuration: Vec<MemoryConfig> = Vec::new();
+    let mut memory_map: Vec<MemoryMap> = Vec::new();
+    let mut symbols: Vec<(Option<u32>, Symbol)> = Vec::new();
+
+    let mut parser_state = ParserState::None;
+    // Bytes per address unit, 2 for C2000
+    let mut unit = 1;
+    // armcl sets bit 0 of Thumb function addresses
+    let mut thumb = false;
+    // The `PAGE n:` heading of the memories being listed, only printed by C2000
+    let mut page: Option<u32> = None;
+    // Output section names longer than their column are printed on a line of their own
+    let mut long_name: Option<String> = None;
+    // Input sections of a section with a `RUN ADDR` are listed at their load address
+    let mut run_offset = 0;
+
+    while let Some(line) = lines.next_line()? {
+        let trimmed = line.trim();
+        if trimmed.contains(" Linker ") && memory_map.is_empty() {
+            // The banner names the target: `TI ARM Linker`, `MSP430 Linker`, `TMS320C2000 Linker`
+            unit = if trimmed.contains("C2000") { 2 } else { 1 };
+            thumb = trimmed.contains("ARM");
+            continue;
+        }
+        if trimmed.starts_with("MEMORY CONFIGURATION") {
+            parser_state = ParserState::MemoryConfiguration;
+            continue;
+        } else if trimmed.starts_with("SECTION ALLOCATION MAP") {
+            parser_state = ParserState::SectionAllocationMap;
+            continue;
+        } else if trimmed.starts_with("GLOBAL SYMBOLS: SORTED BY Symbol Address") {
+            parser_state = ParserState::GlobalSymbols;
+            continue;
+        } else if trimmed.starts_with("GLOBAL SYMBOLS") || is_heading(&line) {
+            // The symbols are also listed sorted by name, and other tables are not read
+            parser_state = ParserState::Skipped;
+            continue;
+        }
+
+        if trimmed.is_empty() || trimmed.starts_with("---") {
+            continue;
+        }
+
+        match parser_state {
+            ParserState::MemoryConfiguration => {
+                if trimmed.starts_with("name") {
+                    continue;
+                }
+                // C2000 lists the program (`PAGE 0:`) and data (`PAGE 1:`) memories apart
+                if let Some(number) = trimmed.strip_prefix("PAGE ").and_then(|text| text.strip_suffix(':')) {
+                    page = Some(parse_page(number)?);
+                    continue;
+                }
+                // `name  origin  length  used  unused  attr  fill`
+                if trimmed.split_whitespace().count() < 5 {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                memory_configuration.push(MemoryConfig {
+                    page,
+                    ..parse_memory_configuration_line(trimmed, unit)?
+                });
+            }
+            ParserState::SectionAllocationMap => {
+                if trimmed.starts_with("output") || trimmed.starts_with("section ") {
+                    continue;
+                }
+                if line.starts_with(' ') {
+                    // `address  length  input section`
+                    if trimmed.split_whitespace().count() < 3 {
+                        lines.unrecognized()?;
+                        continue;
+                    }
+                    if let Some(last_map) = memory_map.last_mut() {
+                        parse_input_section_line(trimmed, unit, run_offset, last_map)?;
+                    }
+                    continue;
+                }
+
+                let (name, rest) = split_token(trimmed);
+                if rest.is_empty() {
+                    long_name = Some(name.to_string());
+                    continue;
+                }
+                let name = match long_name.take() {
+                    Some(long_name) if name == "*" => long_name,
+                    _ => name.to_string(),
+                };
+                let (section_page, rest) = split_token(rest);
+                let (origin, rest) = split_token(rest);
+                let (length, attributes) = split_token(rest);
+                if length.is_empty() {
+                    lines.unrecognized()?;
+                    continue;
+                }
+                // Every section is on page 0 on targets whose memories are not paged
+                let section_page = match page {
+                    Some(_) => Some(parse_page(section_page)?),
+                    None => None,
+                };
+                let origin = parse_bare_hex(origin)?;
+                let length = parse_bare_hex(length)? * unit;
+
+                // `RUN ADDR = 00008000` when the section is loaded and run at different addresses
+                let run_address = match attributes.split_once("RUN ADDR = ") {
+                    Some((_, run_address)) => Some(parse_bare_hex(split_token(run_address).0)?),
+                    None => None,
+                };
+                run_offset = run_address.map(|run_address| run_address.wrapping_sub(origin)).unwrap_or(0);
+                memory_map.push(MemoryMap {
+                    section: name,
+                    page: section_page,
+                    section_address: Some(run_address.unwrap_or(origin)),
+                    section_length: Some(length),
+                    load_address: run_address.map(|_| origin),
+                    ..Default::default()
+                });
+            }
+            ParserState::GlobalSymbols => {
+                if trimmed.starts_with("address") || trimmed.starts_with("page") || trimmed.starts_with('[') {
+                    continue;
+                }
+                // C2000 prints the page before the address
+                let tokens = trimmed.split_whitespace().collect::<Vec<_>>();
+                let (symbol_page, address, name) = match tokens.as_slice() {
+                    [symbol_page, address, name] => (Some(parse_page(symbol_page)?), address, name),
+                    [address, name] => (None, address, name),
+                    _ => {
+                        lines.unrecognized()?;
+                        continue;
+                    }
+                };
+                symbols.push((
+                    symbol_page,
+                    Symbol {
+                        address: parse_bare_hex(address)?,
+                        name: name.to_string(),
+                        size: 0,
+                    },
+                ));
+            }
+            ParserState::None => {
+                // The banner, output file and entry point before the first table
+                if !(trimmed.starts_with('*')
+                    || trimmed.starts_with(">>")
+                    || trimmed.starts_with("OUTPUT FILE NAME:")
+                    || trimmed.starts_with("ENTRY POINT SYMBOL:"))
+                {
+                    lines.unrecognized()?;
+                }
+            }
+            ParserState::Skipped => {
+                // Tables which are not read, such as the segment allocation map and the module summary
+            }
+        }
+    }
+
+    attach_symbols(&mut memory_map, symbols, unit, thumb);
+
+    for sub_section in memory_map.iter_mut().flat_map(|m| m.sub_section.iter_mut()) {
+        infer_word_symbol_sizes(sub_section, unit);
+    }
+
+    Ok(MapFile {
+        memory_configuration,
+        memory_map,
+        address_unit: (unit > 1).then_some(unit),
+        ..Default::default()
+    })
+}
+
+/// Table headings such as `MODULE SUMMARY` are upper case and start in the first column.
+fn is_heading(line: &str) -> bool {
+    !line.starts_with(' ')
+        && line.contains(' ')
+        && !line.starts_with("PAGE ")
+        && line.chars().all(|c| c.is_ascii_uppercase() || c == ' ' || c == ':')
+}
+
+fn parse_page(text: &str) -> Result<u32, ParseError> {
+    text.parse().map_err(|_| ParseError::invalid_number(text))
+}
+
+/// `name  origin  length  used  unused  attr  fill`
+fn parse_memory_configuration_line(line: &str, unit: u64) -> Result<MemoryConfig, ParseError> {
+    let (name, rest) = split_token(line);
+    let (origin, rest) = split_token(rest);
+    let (length, rest) = split_token(rest);
+    let (_used, rest) = split_token(rest);
+    let (_unused, rest) = split_token(rest);
+    // The attributes may contain spaces (`R  X`) and are followed by an optional fill value
+    let rest = rest.trim();
+    let attribute = match rest.rsplit_once(' ') {
+        Some((attribute, fill)) if u64::from_str_radix(fill, 16).is_ok() => attribute.trim(),
+        _ => rest,
+    };
+    Ok(MemoryConfig {
+        name: name.to_string(),
+        origin: parse_bare_hex(origin)?,
+        length: parse_bare_hex(length)? * unit,
+        attribute: attribute.to_string(),
+        ..Default::default()
+    })
+}
+
+/// `address  length  object (section)`, `address  length  library : member (section)`
+/// or `address  length  --HOLE-- [fill = 0]`
+fn parse_input_section_line(line: &str, unit: u64, run_offset: u64, last_map: &mut MemoryMap) -> Result<(), ParseError> {
+    let (address, rest) = split_token(line);
+    let (length, rest) = split_token(rest);
+    let address = parse_bare_hex(address)?.wrapping_add(run_offset);
+    let length = parse_bare_hex(length)? * unit;
+
+    if let Some(hole) = rest.strip_prefix("--HOLE--") {
+        let pattern = hole
+            .trim()
+            .strip_prefix("[fill = ")
+            .and_then(|fill| fill.strip_suffix(']'))
+            .map(|fill| fill.to_string());
+        last_map.fills.push(Fill {
+            address,
+            size: length,
+            pattern,
+        });
+        return Ok(());
+    }
+
+    // Generated sections carry a note such as `[load image, compression = lzss]`
+    let rest = match rest.rsplit_once(" [") {
+        Some((rest, note)) if note.ends_with(']') => rest,
+        _ => rest,
+    };
+    // Sections created by the linker have no object: `(.common:counter)`
+    let (object, section) = match rest.strip_suffix(')').and_then(|rest| rest.rsplit_once('(')) {
+        Some((object, section)) => (object.trim(), section),
+        None => ("", rest),
+    };
+    let object = match object.split_once(" : ") {
+        Some((library, member)) => format!("{}({})", library, member),
+        None => object.to_string(),
+    };
+    last_map.sub_section.push(SubSection {
+        name: vec![section.to_string()],
+        address: Some(address),
+        length: Some(length),
+        object,
+        ..Default::default()
+    });
+    Ok(())
+}
+
+/// The symbol table gives no object or section, so symbols are attached to the input
+/// section of their page containing their address.
+fn attach_symbols(memory_map: &mut [MemoryMap], symbols: Vec<(Option<u32>, Symbol)>, unit: u64, thumb: bool) {
+    let contains = |sub_section: &SubSection, address: u64| match (sub_section.address, sub_section.length) {
+        (Some(start), Some(length)) => (start..start + length / unit).contains(&address),
+        _ => false,
+    };
+
+    for (page, mut symbol) in symbols {
+        // Thumb functions have bit 0 set, data at an odd address does not
+        if thumb && symbol.address & 1 == 1 {
+            let address = thumb_address(symbol.address);
+            let in_code = memory_map.iter().flat_map(|m| m.sub_section.iter()).any(|sub_section| {
+                contains(sub_section, address) && sub_section.name.iter().any(|name| name.starts_with(".text"))
+            });
+            if in_code {
+                symbol.address = address;
+            }
+        }
+        let sub_section = memory_map
+            .iter_mut()
+            .filter(|m| m.page == page)
+            .flat_map(|m| m.sub_section.iter_mut())
+            .find(|sub_section| contains(sub_section, symbol.address));
+        if let Some(sub_section) = sub_section {
+            sub_section.symbols.push(symbol);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::error::ParseError;
+use crate::model::{SubSection, Symbol, SymbolAssignment};
+
+const ASSIGNMENT_OPERATORS: [&str; 9] = ["=", "+=", "-=", "*=", "/=", "<<=", ">>=", "&=", "|="];
+
+/// Parse a `0x` prefixed hexadecimal number as printed by the linker.
+pub(crate) fn parse_hex(text: &str) -> Option<u64> {
+    let digits = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"))?;
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// Parse a hexadecimal number printed without a `0x` prefix, as by lld, MSVC and the TI linker.
+pub(crate) fn parse_bare_hex(text: &str) -> Result<u64, ParseError> {
+    u64::from_str_radix(text, 16).map_err(|_| ParseError::invalid_number(text))
+}
+
+/// The address of a Thumb function, whose symbol value has bit 0 set.
+pub(crate) fn thumb_address(value: u64) -> u64 {
+    value & !1
+}
+
+/// Split off the first whitespace separated token, keeping the rest of the text
+/// intact so that paths containing spaces survive.
+pub(crate) fn split_token(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.split_once(char::is_whitespace) {
+        Some((token, rest)) => (token, rest.trim_start()),
+        None => (text, ""),
+    }
+}
+
+/// Split `libc.a(printf.o)` into the archive and the member.
+pub(crate) fn split_archive_member(text: &str) -> (&str, &str) {
+    text.strip_suffix(')')
+        .and_then(|text| text.rsplit_once('('))
+        .unwrap_or((text, ""))
+}
+
+/// Parse a linker script statement such as `_etext = .` or `PROVIDE (end = .)`.
+pub(crate) fn parse_assignment_statement(address: Option<u64>, statement: &str) -> Option<SymbolAssignment> {
+    let statement = statement.trim();
+    let (provided, statement) = match statement
+        .strip_prefix("PROVIDE_HIDDEN")
+        .or_else(|| statement.strip_prefix("PROVIDE"))
+    {
+        Some(inner) => {
+            let inner = inner.trim();
+            (true, inner.strip_prefix('(')?.strip_suffix(')')?.trim())
+        }
+        None => (false, statement),
+    };
+
+    // The operator is always surrounded by spaces, `operator=` in a demangled name is not
+    let (index, operator) = ASSIGNMENT_OPERATORS
+        .iter()
+        .filter_map(|operator| statement.find(&format!(" {} ", operator)).map(|index| (index, *operator)))
+        .min_by_key(|(index, _)| *index)?;
+    let symbol = statement[..index].trim();
+    let value = statement[index + operator.len() + 2..].trim();
+    let expression = match operator.strip_suffix('=') {
+        Some("") | None => value.to_string(),
+        Some(op) => format!("{} {} {}", symbol, op, value),
+    };
+
+    Some(SymbolAssignment {
+        address,
+        symbol: symbol.to_string(),
+        expression,
+        provided,
+    })
+}
+
+/// Infer symbol sizes from the next symbol address or the end of the input section.
+pub(crate) fn infer_symbol_sizes(sub_section: &mut SubSection) {
+    infer_word_symbol_sizes(sub_section, 1);
+}
+
+/// Infer symbol sizes, in bytes, of a map whose addresses count `unit` bytes.
+pub(crate) fn infer_word_symbol_sizes(sub_section: &mut SubSection, unit: u64) {
+    let end = match (sub_section.address, sub_section.length) {
+        (Some(address), Some(length)) => Some(address + length / unit),
+        _ => None,
+    };
+    let mut addresses: Vec<u64> = sub_section.symbols.iter().map(|symbol| symbol.address).collect();
+    addresses.sort_unstable();
+    addresses.dedup();
+
+    for symbol in &mut sub_section.symbols {
+        let next = addresses
+            .iter()
+            .copied()
+            .find(|&address| address > symbol.address)
+            .or(end);
+        symbol.size = next.map(|next| next.saturating_sub(symbol.address) * unit).unwrap_or(0);
+    }
+}
+
+/// Split a section of `length` bytes at `address` into one input section per run of
+/// consecutive symbols defined by the same object, for map files which list symbols
+/// with their object but not the input sections. `symbols` are sorted by address.
+pub(crate) fn split_at_symbols(
+    name: &str,
+    address: u64,
+    length: u64,
+    symbols: impl IntoIterator<Item = (String, Symbol)>,
+) -> Vec<SubSection> {
+    // The first input section covers the start of the section, whatever its first symbol
+    let mut starts: Vec<u64> = vec![address];
+    let mut sub_sections: Vec<SubSection> = vec![SubSection {
+        name: vec![name.to_string()],
+        ..Default::default()
+    }];
+    for (object, symbol) in symbols {
+        let last = sub_sections.len() - 1;
+        if sub_sections[last].object != object {
+            if sub_sections[last].symbols.is_empty() && starts[last] == symbol.address {
+                sub_sections[last].object = object;
+            } else {
+                starts.push(symbol.address);
+                sub_sections.push(SubSection {
+                    name: vec![name.to_string()],
+                    object,
+                    ..Default::default()
+                });
+            }
+        }
+        if let Some(sub_section) = sub_sections.last_mut() {
+            sub_section.symbols.push(symbol);
+        }
+    }
+
+    starts.push(address + length);
+    for (sub_section, range) in sub_sections.iter_mut().zip(starts.windows(2)) {
+        sub_section.address = Some(range[0]);
+        sub_section.length = Some(range[1] - range[0]);
+    }
+    sub_sections
+}
@@ -0,0 +1,44 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn fixture(name: &str) -> Vec<u8> {
+    fs::read(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+/// Run mapa on `input` given on standard input and return the memory map it wrote.
+fn run_stdin(name: &str, input: &[u8]) -> String {
+    let out_path: PathBuf = std::env::temp_dir().join(format!("mapa-cli-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&out_path).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mapa"))
+        .args(["-o", out_path.to_str().unwrap(), "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    assert!(child.wait().unwrap().success());
+
+    let memory_map = fs::read_to_string(out_path.join("memory_map.tsv")).unwrap();
+    fs::remove_dir_all(&out_path).unwrap();
+    memory_map
+}
+
+#[test]
+fn compressed_stdin() {
+    let map = fixture("gold.map");
+    let expected = run_stdin("plain", &map);
+    assert!(expected.contains(".text.main"));
+
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&map).unwrap();
+    assert_eq!(run_stdin("gzip", &gzip.finish().unwrap()), expected);
+
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+    xz.write_all(&map).unwrap();
+    assert_eq!(run_stdin("xz", &xz.finish().unwrap()), expected);
+
+    assert_eq!(run_stdin("zstd", &zstd::encode_all(&map[..], 0).unwrap()), expected);
+}
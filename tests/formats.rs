@@ -0,0 +1,632 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use mapa::{ErrorKind, Format, MapFile, ParseOptions, Warning};
+
+fn parse_fixture(name: &str, format: Format) -> MapFile {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let file = File::open(&path).unwrap();
+    // Every line of the fixtures is recognized
+    mapa::parse_format_with(BufReader::new(file), format, &ParseOptions { strict: true }).unwrap()
+}
+
+/// gold and mold maps of the same link describe the same sections and symbols.
+fn assert_common_layout(map: &MapFile) {
+    let sections: Vec<_> = map.memory_map.iter().map(|m| m.section.as_str()).collect();
+    assert_eq!(sections, [".interp", ".text", ".data", ".bss"]);
+
+    let text = &map.memory_map[1];
+    assert_eq!(text.section_length, Some(0x90));
+    assert_eq!(text.load_address, None);
+    assert_eq!(text.sub_section.len(), 3);
+
+    let main = &text.sub_section[1];
+    assert_eq!(main.name, [".text.main"]);
+    assert_eq!(main.object, "obj/main.o");
+    assert_eq!(main.address.unwrap() - text.section_address.unwrap(), 0x30);
+    assert_eq!(main.length, Some(0x40));
+    assert_eq!(main.symbols.len(), 1);
+    assert_eq!(main.symbols[0].name, "main");
+    assert_eq!(main.symbols[0].size, 0x40);
+
+    let printf = &text.sub_section[2];
+    assert_eq!(printf.object, "/usr/lib/x86_64-linux-gnu/libc.a(printf.o)");
+    assert_eq!(printf.symbols[0].name, "printf");
+
+    let bss = &map.memory_map[3];
+    assert_eq!(bss.sub_section[0].name, ["COMMON"]);
+    assert_eq!(bss.sub_section[0].symbols[0].name, "counter");
+    assert_eq!(bss.sub_section[0].symbols[0].size, 8);
+}
+
+#[test]
+fn gnu() {
+    let map = parse_fixture("gnu.map", Format::Gnu);
+
+    let data = map.memory_map.iter().find(|m| m.section == ".data").unwrap();
+    assert_eq!(data.section_address, Some(0x20000000));
+    assert_eq!(data.load_address, Some(0x200));
+
+    // `.comment`, `.ARM.attributes` and `.debug_info` are at address 0, like FLASH,
+    // but take no memory. `.data` counts against FLASH at its load address, `.bss`
+    // is given one too but is not loaded.
+    let bss = map.memory_map.iter().find(|m| m.section == ".bss").unwrap();
+    assert_eq!(bss.load_address, Some(0x210));
+    let usage = map.region_usage();
+    assert_eq!(usage[0].name, "FLASH");
+    assert_eq!(usage[0].used, 0xc0 + 0x140 + 0x10);
+    assert_eq!(usage[1].name, "RAM");
+    assert_eq!(usage[1].used, 0x10 + 0x100);
+}
+
+#[test]
+fn gnu_cross_reference() {
+    let map = parse_fixture("gnu.map", Format::Gnu);
+    let symbols: Vec<_> = map.cross_references.iter().map(|cref| cref.symbol.as_str()).collect();
+    assert_eq!(
+        symbols,
+        ["Reset_Handler", "_ZN9namespace12a_long_class_name16a_long_method_nameEv", "exit", "main", "printf"]
+    );
+
+    let reset_handler = map.cross_reference("Reset_Handler").unwrap();
+    assert_eq!(reset_handler.defined_in, "build/startup.o");
+    assert!(reset_handler.referenced_by.is_empty());
+
+    // A symbol wider than its column is printed alone, its files on the next lines
+    let method = &map.cross_references[1];
+    assert_eq!(method.defined_in, "build/main.o");
+    assert_eq!(method.referenced_by, ["build/startup.o"]);
+
+    let exit = map.cross_reference("exit").unwrap();
+    assert_eq!(exit.defined_in, "/opt/gcc/arm-none-eabi/lib/thumb/libc_nano.a(lib_a-exit.o)");
+    assert_eq!(exit.referenced_by, ["build/startup.o"]);
+}
+
+#[test]
+fn gnu_input_files() {
+    let map = parse_fixture("gnu.map", Format::Gnu);
+    let files: Vec<_> = map.input_files.iter().map(|file| (file.path.as_str(), file.group)).collect();
+    assert_eq!(
+        files,
+        [
+            ("/opt/gcc/lib/crti.o", None),
+            ("build/startup.o", None),
+            ("build/main.o", None),
+            ("/opt/gcc/arm-none-eabi/lib/thumb/libc_nano.a", Some(0)),
+            ("/opt/gcc/arm-none-eabi/lib/thumb/libnosys.a", Some(0)),
+        ]
+    );
+
+    let output = map.output.as_ref().unwrap();
+    assert_eq!(output.file, "build/firmware.elf");
+    assert_eq!(output.target, "elf32-littlearm");
+}
+
+#[test]
+fn gnu_assignments() {
+    let map = parse_fixture("gnu.map", Format::Gnu);
+
+    // Outside of any output section
+    assert_eq!(map.assignments.len(), 3);
+    assert_eq!(map.assignments[0].symbol, "_estack");
+    assert_eq!(map.assignments[0].address, Some(0x20010000));
+    assert_eq!(map.assignments[0].expression, "(ORIGIN (RAM) + LENGTH (RAM))");
+    assert!(!map.assignments[0].provided);
+    // `[!provide]` marks a PROVIDE of a symbol which is never referenced
+    assert_eq!(map.assignments[2].symbol, "__stack_size");
+    assert_eq!(map.assignments[2].address, None);
+    assert!(map.assignments[2].provided);
+
+    let isr_vector = &map.memory_map[0];
+    assert_eq!(isr_vector.assignments[0].symbol, ".");
+    assert_eq!(isr_vector.assignments[0].expression, "ALIGN (0x4)");
+
+    let text = &map.memory_map[1];
+    assert_eq!(text.assignments.len(), 1);
+    assert_eq!(text.assignments[0].symbol, "_etext");
+    assert_eq!(text.assignments[0].address, Some(0x200));
+    assert!(text.assignments[0].provided);
+    // Assignments are not taken as symbols of the input section before them
+    let exit = text.sub_section.last().unwrap();
+    let names: Vec<_> = exit.symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, ["exit"]);
+
+    let data = &map.memory_map[2];
+    let symbols: Vec<_> = data.assignments.iter().map(|assignment| assignment.symbol.as_str()).collect();
+    assert_eq!(symbols, ["_sdata", "_edata"]);
+}
+
+#[test]
+fn gnu_address_width() {
+    // 32-bit targets print a 10 character address column, 64-bit targets 18, and both
+    // a 10 character size column
+    let map32 = parse_fixture("gnu.map", Format::Gnu);
+    let map64 = parse_fixture("gnu64.map", Format::Gnu);
+
+    for map in [&map32, &map64] {
+        let memory: Vec<_> = map
+            .memory_configuration
+            .iter()
+            .map(|memory| (memory.name.as_str(), memory.origin, memory.length, memory.attribute.as_str()))
+            .collect();
+        assert_eq!(memory, [("FLASH", 0, 0x80000, "xr"), ("RAM", 0x20000000, 0x10000, "xrw")]);
+
+        let exit = map.memory_map[1].sub_section.last().unwrap();
+        assert_eq!(exit.name, [".text.exit"]);
+        assert_eq!(exit.address, Some(0x190));
+        assert_eq!(exit.length, Some(0x70));
+        assert_eq!(exit.object, "/opt/gcc/arm-none-eabi/lib/thumb/libc_nano.a(lib_a-exit.o)");
+        assert_eq!(map.memory_map[1].fills[0].size, 0xc);
+        assert_eq!(map.memory_map[1].fills[0].pattern.as_deref(), Some("ff"));
+        // `LONG 0x12345678` is data written by the linker script
+        assert_eq!(map.memory_map[1].fills[1].address, 0x13c);
+        assert_eq!(map.memory_map[1].fills[1].size, 0x4);
+        assert_eq!(map.memory_map[1].fills[1].pattern.as_deref(), Some("12345678"));
+        assert_eq!(map.memory_map[1].sub_section.len(), 4);
+        assert_eq!(map.discarded_sections[1].size, 0x14);
+        // Shared libraries linked as needed are not archive members
+        assert_eq!(map.archive_members.len(), 2);
+        assert_eq!(map.archive_members[1].member, "lib_a-printf.o");
+    }
+    assert_eq!(format!("{:?}", map32.memory_map), format!("{:?}", map64.memory_map));
+    assert_eq!(format!("{:?}", map32.assignments), format!("{:?}", map64.assignments));
+}
+
+#[test]
+fn gold() {
+    let map = parse_fixture("gold.map", Format::Gold);
+    assert_common_layout(&map);
+
+    assert_eq!(map.memory_map[1].section_address, Some(0x401000));
+    assert_eq!(map.memory_map[0].sub_section[0].object, "**linker generated**");
+    assert!(map.memory_configuration.is_empty());
+
+    assert_eq!(map.archive_members.len(), 2);
+    assert_eq!(map.archive_members[0].member, "printf.o");
+    assert_eq!(map.archive_members[0].referenced_by, "obj/main.o");
+    assert_eq!(map.archive_members[0].symbol, "printf");
+    let chain = map.why_linked("vfprintf-internal.o").unwrap();
+    assert_eq!(chain.len(), 2);
+    assert_eq!(chain[0].referenced_by, "obj/main.o");
+
+    assert_eq!(map.common_symbols.len(), 1);
+    assert_eq!(map.common_symbols[0].symbol, "counter");
+    assert_eq!(map.common_symbols[0].size, 8);
+
+    assert_eq!(map.discarded_sections.len(), 1);
+    assert_eq!(map.discarded_sections[0].section, ".text.unused");
+    assert_eq!(map.discarded_sections[0].size, 0x20);
+
+    let fills = &map.memory_map[1].fills;
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].address, 0x401026);
+    assert_eq!(fills[0].size, 0xa);
+    assert_eq!(fills[0].pattern.as_deref(), Some("90"));
+}
+
+#[test]
+fn lld() {
+    let map = parse_fixture("lld.map", Format::Lld);
+
+    let sections: Vec<_> = map.memory_map.iter().map(|m| m.section.as_str()).collect();
+    assert_eq!(sections, [".isr_vector", ".text", ".data", ".bss", ".comment"]);
+
+    // lld prints the location counter of an assignment, the value of an expression is unknown
+    assert_eq!(map.assignments.len(), 2);
+    assert_eq!(map.assignments[0].symbol, "_estack");
+    assert_eq!(map.assignments[0].expression, "ORIGIN(RAM) + LENGTH(RAM)");
+    assert_eq!(map.assignments[0].address, None);
+    assert_eq!(map.assignments[1].symbol, "_Min_Heap_Size");
+    assert_eq!(map.assignments[1].address, Some(0x200));
+
+    let text = &map.memory_map[1];
+    assert_eq!(text.assignments[0].symbol, "_etext");
+    assert_eq!(text.assignments[0].address, Some(0x08000270));
+    let printf = &text.sub_section[3];
+    assert_eq!(printf.name, [".text.printf"]);
+    assert_eq!(printf.object, "/opt/lib/libc.a(lib_a-printf.o)");
+    assert_eq!(printf.symbols[0].name, "printf");
+    assert_eq!(printf.symbols[0].size, 0x50);
+
+    let data = &map.memory_map[2];
+    assert_eq!(data.section_address, Some(0x20000000));
+    assert_eq!(data.load_address, Some(0x08000270));
+    assert_eq!(data.sub_section[0].symbols[0].name, "counter");
+    assert_eq!(data.assignments.len(), 3);
+    assert!(data.assignments[1].provided);
+    assert_eq!(data.assignments[1].symbol, "__data_end__");
+    assert_eq!(data.assignments[1].address, Some(0x20000010));
+
+    // Sections at the same address as their output section have no load address
+    assert_eq!(map.memory_map[3].load_address, None);
+
+    // A header with its columns out of order is not the lld header
+    let text = "x Symbol y In z Out\n        08000000         08000000      188     4 .isr_vector\n";
+    let map = mapa::parse_format(text.as_bytes(), Format::Lld).unwrap();
+    assert!(map.memory_map.is_empty());
+    assert_eq!(map.warnings.len(), 2);
+    assert_eq!(map.warnings[0].text, "x Symbol y In z Out");
+}
+
+#[test]
+fn mold() {
+    let map = parse_fixture("mold.map", Format::Mold);
+    assert_common_layout(&map);
+
+    assert_eq!(map.memory_map[1].section_address, Some(0x201000));
+    assert_eq!(map.memory_map[0].sub_section[0].object, "<internal>");
+    assert!(map.archive_members.is_empty());
+}
+
+#[test]
+fn armlink() {
+    let map = parse_fixture("armlink.map", Format::Armlink);
+
+    let regions: Vec<_> = map.memory_configuration.iter().map(|memory| memory.name.as_str()).collect();
+    assert_eq!(regions, ["ER_IROM1", "RW_IRAM1"]);
+    assert_eq!(map.memory_configuration[0].length, 0x80000);
+
+    let rom = &map.memory_map[0];
+    let main = rom.sub_section.iter().find(|sub| sub.name == [".text.main"]).unwrap();
+    assert_eq!(main.object, "main.o");
+    assert_eq!(main.symbols[0].name, "main");
+    assert_eq!(main.symbols[0].address, 0x080001c8);
+    assert_eq!(main.symbols[0].size, 48);
+
+    let printf = rom.sub_section.iter().find(|sub| sub.name == ["i.__2printf"]).unwrap();
+    assert_eq!(printf.object, "c_w.l(printfa.o)");
+    assert_eq!(printf.symbols[0].name, "__2printf");
+
+    let ram = &map.memory_map[1];
+    assert_eq!(ram.load_address, Some(0x08000238));
+    assert_eq!(ram.load_length, Some(4));
+    assert_eq!(rom.fill_size(), 4);
+    assert_eq!(ram.fill_size(), 12);
+
+    // Zero initialized data is not counted in the load region
+    let usage = map.region_usage();
+    assert_eq!(usage[0].used, 0x23c);
+    assert_eq!(usage[1].used, 0x110);
+
+    assert_eq!(map.object_sizes.len(), 4);
+    let main = map.object_sizes.iter().find(|size| size.object == "main.o").unwrap();
+    assert_eq!(main.zi_data, 256);
+}
+
+#[test]
+fn iar() {
+    let map = parse_fixture("iar.map", Format::Iar);
+
+    assert_eq!(map.memory_configuration.len(), 2);
+    assert_eq!(map.memory_configuration[0].name, "P1");
+    assert_eq!(map.memory_configuration[0].origin, 0x0800_0000);
+    assert_eq!(map.memory_configuration[0].length, 0x80000);
+    assert_eq!(map.memory_configuration[1].attribute, "rw, block CSTACK, block HEAP");
+
+    let sections: Vec<_> = map.memory_map.iter().map(|m| m.section.as_str()).collect();
+    assert_eq!(sections, ["A0", "P1", "P2", "P2", "P2"]);
+    assert_eq!(map.memory_map[1].section_length, Some(0x1fc));
+    assert_eq!(map.memory_map[2].section_address, Some(0x2000_0000));
+
+    let p1 = &map.memory_map[1];
+    let main = &p1.sub_section[0];
+    assert_eq!(main.name, [".text"]);
+    assert_eq!(main.object, "main.o");
+    assert_eq!(main.symbols[0].name, "main");
+    assert_eq!(main.symbols[0].address, 0x0800_0040);
+    assert_eq!(main.symbols[0].size, 0x5c);
+    assert_eq!(p1.sub_section[2].object, "m7M_tl.a(printf.o)");
+    assert_eq!(p1.sub_section[5].name, ["Initializer bytes"]);
+    assert_eq!(p1.sub_section[9].symbols[0].name, "__iar_init_vfp_with_a_rather_long_name");
+
+    // Block headers are not input sections, the sections placed in them are
+    assert_eq!(map.memory_map[2].sub_section.len(), 2);
+    assert_eq!(map.memory_map[3].sub_section[0].object, "<Block tail>");
+
+    let usage = map.region_usage();
+    assert_eq!(usage[0].used, 0x23c);
+    assert_eq!(usage[1].used, 0x908);
+
+    assert_eq!(map.object_sizes.len(), 6);
+    let main = &map.object_sizes[0];
+    assert_eq!((main.code, main.ro_data, main.rw_data), (92, 24, 260));
+    assert_eq!(map.object_sizes[1].ro_data, 0);
+    assert_eq!(map.object_sizes[3].object, "m7M_tl.a(printf.o)");
+}
+
+#[test]
+fn ti_arm() {
+    let map = parse_fixture("ti_arm.map", Format::Ti);
+
+    assert_eq!(map.memory_configuration.len(), 2);
+    assert_eq!(map.memory_configuration[1].name, "SRAM");
+    assert_eq!(map.memory_configuration[1].origin, 0x2000_0000);
+    assert_eq!(map.memory_configuration[1].attribute, "RW X");
+
+    let sections: Vec<_> = map.memory_map.iter().map(|m| m.section.as_str()).collect();
+    assert_eq!(sections, [".intvecs", ".text", ".cinit", ".init_array", ".stack", ".bss", ".data"]);
+
+    let text = &map.memory_map[1];
+    assert_eq!(text.sub_section[0].name, [".text:main"]);
+    assert_eq!(text.sub_section[0].object, "main.obj");
+    // The Thumb bit is cleared and the size inferred from the input section
+    assert_eq!(text.sub_section[0].symbols[0].name, "main");
+    assert_eq!(text.sub_section[0].symbols[0].address, 0x26c);
+    assert_eq!(text.sub_section[0].symbols[0].size, 0x9c);
+    assert_eq!(text.sub_section[1].object, "rtsv7M4_T_le_v4SPD16_eabi.lib(boot_cortex_m.c.obj)");
+    assert_eq!(text.fills[0].size, 4);
+    assert_eq!(text.fills[0].pattern.as_deref(), Some("0"));
+
+    let cinit = &map.memory_map[2];
+    assert_eq!(cinit.sub_section[1].name, [".cinit..data.load"]);
+    assert_eq!(cinit.sub_section[1].object, "");
+    assert_eq!(map.memory_map[5].sub_section[0].symbols[0].name, "counter");
+
+    let usage = map.region_usage();
+    assert_eq!(usage[0].used, 0x42c);
+    assert_eq!(usage[1].used, 0x208);
+}
+
+#[test]
+fn ti_c2000() {
+    let map = parse_fixture("ti_c2000.map", Format::Ti);
+
+    // Word addresses are kept, lengths are converted to bytes
+    assert_eq!(map.address_unit, Some(2));
+    assert_eq!(map.memory_configuration.len(), 5);
+    let flashb = &map.memory_configuration[3];
+    assert_eq!(flashb.name, "FLASHB");
+    assert_eq!(flashb.page, Some(0));
+    assert_eq!(flashb.origin, 0x8_2000);
+    assert_eq!(flashb.length, 0x4000);
+    assert_eq!(flashb.attribute, "RWIX");
+    assert_eq!(map.memory_configuration[4].page, Some(1));
+
+    assert_eq!(map.memory_map[0].section, "codestart");
+    let text = &map.memory_map[1];
+    assert_eq!(text.section_length, Some(0x230));
+    assert_eq!(text.sub_section[1].address, Some(0x8_2080));
+    assert_eq!(text.sub_section[1].symbols[0].name, "_c_int00");
+    // Sizes are in bytes, addresses in words
+    assert_eq!(text.sub_section[0].symbols[0].size, 0x100);
+
+    let ramfuncs = &map.memory_map[2];
+    assert_eq!(ramfuncs.section_address, Some(0x8000));
+    assert_eq!(ramfuncs.load_address, Some(0x8_2118));
+    assert_eq!(ramfuncs.sub_section[0].address, Some(0x8000));
+    assert_eq!(ramfuncs.sub_section[0].symbols[0].name, "motor_isr");
+
+    // Program and data memory both have an address 0x100
+    let ramtext = &map.memory_map[3];
+    assert_eq!(ramtext.page, Some(0));
+    assert_eq!(ramtext.sub_section[0].symbols[0].name, "fast_copy");
+    let ebss = &map.memory_map[4];
+    assert_eq!(ebss.page, Some(1));
+    assert_eq!(ebss.sub_section[0].symbols[0].name, "counter");
+    assert_eq!(ebss.sub_section[0].symbols[0].size, 0x20);
+
+    let usage = map.region_usage();
+    let used: Vec<_> = usage.iter().map(|region| (region.name.as_str(), region.used)).collect();
+    assert_eq!(used, [("BEGIN", 0x4), ("RAMM0", 0x10), ("RAMLS0", 0x20), ("FLASHB", 0x250), ("RAMM1", 0x220)]);
+}
+
+#[test]
+fn msvc() {
+    let map = parse_fixture("msvc.map", Format::Msvc);
+
+    let sections: Vec<_> = map.memory_map.iter().map(|m| m.section.as_str()).collect();
+    assert_eq!(sections, [".text", ".rdata", ".data"]);
+    assert_eq!(map.memory_map[0].section_address, Some(0x1_4000_1000));
+    assert_eq!(map.memory_map[0].section_length, Some(0x1a0));
+
+    // Consecutive symbols of the same object make up one input section
+    let text = &map.memory_map[0].sub_section;
+    assert_eq!(text.len(), 4);
+    assert_eq!(text[1].name, [".text$mn"]);
+    assert_eq!(text[1].object, "util.obj");
+    assert_eq!(text[1].length, Some(0x60));
+    let names: Vec<_> = text[1].symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, ["?helper@@YAHH@Z", "static_fn", "?scale@@YAHH@Z"]);
+    assert_eq!(text[1].symbols[1].size, 0x10);
+    assert_eq!(text[2].object, "libcmt(printf.obj)");
+    assert_eq!(text[3].name, [".text$x"]);
+
+    // Groups without symbols are kept without an object
+    let rdata = &map.memory_map[1].sub_section;
+    assert_eq!(rdata[0].name, [".idata$5"]);
+    assert_eq!(rdata[0].object, "");
+    assert_eq!(rdata[0].address, Some(0x1_4000_2000));
+
+    let bss = &map.memory_map[2].sub_section[1];
+    assert_eq!(bss.symbols[0].name, "buffer");
+    assert_eq!(bss.symbols[0].size, 0x100);
+}
+
+#[test]
+fn ld64() {
+    let map = parse_fixture("ld64.map", Format::Ld64);
+
+    let output = map.output.as_ref().unwrap();
+    assert_eq!(output.file, "/Users/dev/build/app");
+    assert_eq!(output.target, "arm64");
+
+    let sections: Vec<_> = map.memory_map.iter().map(|m| m.section.as_str()).collect();
+    assert_eq!(
+        sections,
+        ["__TEXT,__text", "__TEXT,__stubs", "__TEXT,__cstring", "__DATA_CONST,__got", "__DATA,__data", "__DATA,__bss"]
+    );
+
+    // File indices are resolved to the object paths
+    let text = &map.memory_map[0].sub_section;
+    assert_eq!(text.len(), 3);
+    assert_eq!(text[0].object, "/Users/dev/build/main.o");
+    assert_eq!(text[0].length, Some(0x40));
+    assert_eq!(text[1].object, "/Users/dev/build/libutil.a(util.o)");
+    assert_eq!(text[1].symbols[0].name, "_helper");
+    assert_eq!(text[1].symbols[0].size, 0x30);
+
+    let cstring = &map.memory_map[2].sub_section;
+    assert_eq!(cstring[0].symbols[0].name, "literal string: hello %d\\n");
+    assert_eq!(cstring[1].address, Some(0x1_0000_3fbe));
+    assert_eq!(map.memory_map[3].sub_section[0].object, "linker synthesized");
+
+    // Dead stripped symbols are not sections
+    assert!(map.discarded_sections.is_empty());
+}
+
+#[test]
+fn detect() {
+    let fixtures = [
+        ("gnu.map", Format::Gnu),
+        ("gnu64.map", Format::Gnu),
+        ("gold.map", Format::Gold),
+        ("lld.map", Format::Lld),
+        ("mold.map", Format::Mold),
+        ("armlink.map", Format::Armlink),
+        ("iar.map", Format::Iar),
+        ("ti_arm.map", Format::Ti),
+        ("ti_c2000.map", Format::Ti),
+        ("msvc.map", Format::Msvc),
+        ("ld64.map", Format::Ld64),
+    ];
+    for (name, format) in fixtures {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let (detected, map) = mapa::parse_detect(BufReader::new(File::open(&path).unwrap())).unwrap();
+        assert_eq!(detected, format, "{}", name);
+        // The lines read for detection are parsed too
+        assert!(!map.memory_map.is_empty(), "{}", name);
+    }
+
+    assert_eq!(Format::detect("Archive member included to satisfy reference by file (symbol)"), Some(Format::Gnu));
+    assert_eq!(Format::detect("             VMA              LMA     Size Align Out     In      Symbol"), Some(Format::Lld));
+    let error = mapa::parse_detect(&b"hello\nworld\n"[..]).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::UnknownFormat);
+}
+
+#[test]
+fn unrecognized_lines() {
+    let mut lines: Vec<&str> = include_str!("fixtures/gold.map").lines().collect();
+    lines.insert(20, "    not a map line");
+    let text = lines.join("\n");
+
+    let map = mapa::parse_format(text.as_bytes(), Format::Gold).unwrap();
+    assert_eq!(map.memory_map.len(), 4);
+    assert_eq!(
+        map.warnings,
+        [Warning {
+            line: 21,
+            text: "    not a map line".to_string(),
+        }]
+    );
+
+    let error = mapa::parse_format_with(text.as_bytes(), Format::Gold, &ParseOptions { strict: true }).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::UnrecognizedLine);
+    assert_eq!(error.line(), Some(21));
+    assert_eq!(error.text(), "    not a map line");
+}
+
+#[test]
+fn unrecognized_table_rows() {
+    // A row which does not match the layout of its table is reported, not taken for a bad number
+    let tables = [
+        ("gnu.map", Format::Gnu, "build/startup.o (exit)"),
+        ("msvc.map", Format::Msvc, "0001:00000180 00000020H"),
+        ("ti_arm.map", Format::Ti, "  FLASH "),
+        ("ti_arm.map", Format::Ti, "main.obj (.text:main)"),
+        ("ld64.map", Format::Ld64, "__TEXT\t__text"),
+        ("ld64.map", Format::Ld64, "[  1] _main"),
+        ("iar.map", Format::Iar, "main.o                 92"),
+        ("iar.map", Format::Iar, "Code  Gb  main.o [1]"),
+        ("armlink.map", Format::Armlink, "120   util.o"),
+    ];
+    for (name, format, row) in tables {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let text = std::fs::read_to_string(path).unwrap();
+        let mut lines: Vec<&str> = text.lines().collect();
+        let index = lines.iter().position(|line| line.contains(row)).unwrap() + 1;
+        lines.insert(index, " note: something");
+
+        let map = mapa::parse_format(lines.join("\n").as_bytes(), format).unwrap();
+        let warning = Warning {
+            line: index + 1,
+            text: " note: something".to_string(),
+        };
+        assert_eq!(map.warnings, [warning], "{} after {:?}", name, row);
+    }
+
+    // A row of the table layout with a bad number is still an error
+    let text = include_str!("fixtures/msvc.map").replace("00000180H", "000001zzH");
+    let error = mapa::parse_format(text.as_bytes(), Format::Msvc).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::InvalidNumber("000001zz".to_string()));
+    assert_eq!(error.line(), Some(8));
+}
+
+#[test]
+fn invalid_number() {
+    let text = include_str!("fixtures/gold.map").replace("counter             0x8 ", "counter             0xzz");
+    let error = mapa::parse_format(text.as_bytes(), Format::Gold).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::InvalidNumber("0xzz".to_string()));
+    assert_eq!(error.line(), Some(11));
+    assert_eq!(error.to_string(), "Invalid number '0xzz' at line 11: counter             0xzz              obj/main.o");
+}
+
+#[test]
+fn invalid_utf8() {
+    // A Latin-1 `é` in an object path
+    let text = include_str!("fixtures/gold.map").replace(" obj/main.o\n", " obj/m\u{1}in.o\n");
+    let bytes: Vec<u8> = text.bytes().map(|byte| if byte == 1 { 0xe9 } else { byte }).collect();
+
+    let map = mapa::parse_format(&bytes[..], Format::Gold).unwrap();
+    let main = &map.memory_map[1].sub_section[1];
+    assert_eq!(main.name, [".text.main"]);
+    assert_eq!(main.object, "obj/m\u{fffd}in.o");
+    assert_eq!(main.symbols[0].name, "main");
+    assert_eq!(map.invalid_utf8_lines, [11, 27, 33, 37]);
+    assert!(map.warnings.is_empty());
+}
+
+#[test]
+fn paths_with_spaces() {
+    // The space of the archive path falls on the last character of the member column
+    let libc = "/opt/gcc-arm-none-eabi-10.3.1 2021.10/lib/libc.a";
+    let text = include_str!("fixtures/gold.map")
+        .replace("obj/", "My Project/obj/")
+        .replace("/usr/lib/x86_64-linux-gnu/libc.a", libc);
+    let map = mapa::parse_format(text.as_bytes(), Format::Gold).unwrap();
+    assert!(map.warnings.is_empty());
+    assert_eq!(map.archive_members[0].archive, libc);
+    assert_eq!(map.archive_members[0].member, "printf.o");
+    assert_eq!(map.archive_members[0].referenced_by, "My Project/obj/main.o");
+    assert_eq!(map.common_symbols[0].file, "My Project/obj/main.o");
+    assert_eq!(map.discarded_sections[0].object, "My Project/obj/util.o");
+    let text_section = &map.memory_map[1];
+    assert_eq!(text_section.sub_section[1].object, "My Project/obj/main.o");
+    assert_eq!(text_section.sub_section[2].object, format!("{}(printf.o)", libc));
+
+    // An empty input section at the address of the next one is kept apart from it
+    let crti = " .text          0x000000c0       0x40 /opt/gcc/lib/crti.o";
+    let startup = " .text          0x000000c0        0x0 My Project/build/startup.o";
+    let text = include_str!("fixtures/gnu.map").replace(crti, &format!("{}\n{}", startup, crti));
+    let map = mapa::parse_format(text.as_bytes(), Format::Gnu).unwrap();
+    assert!(map.warnings.is_empty());
+    let text_section = &map.memory_map[1];
+    assert_eq!(text_section.sub_section[0].object, "My Project/build/startup.o");
+    assert_eq!(text_section.sub_section[0].length, Some(0));
+    assert_eq!(text_section.sub_section[1].object, "/opt/gcc/lib/crti.o");
+    assert_eq!(text_section.sub_section[1].length, Some(0x40));
+
+    let text = include_str!("fixtures/iar.map")
+        .replace("main.o [1]", "my main.o [1]")
+        .replace("    main.o                 92", "    my main.o              92");
+    let map = mapa::parse_format(text.as_bytes(), Format::Iar).unwrap();
+    assert!(map.warnings.is_empty());
+    let main = map.memory_map.iter().flat_map(|m| &m.sub_section).find(|s| s.name == [".text"]).unwrap();
+    assert_eq!(main.object, "my main.o");
+    assert_eq!(main.symbols[0].name, "main");
+    assert_eq!(map.object_sizes[0].object, "my main.o");
+    assert_eq!(map.object_sizes[0].code, 92);
+
+    let text = include_str!("fixtures/msvc.map").replace(" main.obj", r" C:\My Project\main.obj");
+    let map = mapa::parse_format(text.as_bytes(), Format::Msvc).unwrap();
+    assert_eq!(map.memory_map[0].sub_section[0].object, r"C:\My Project\main.obj");
+}